@@ -0,0 +1,76 @@
+use image::{DynamicImage, ImageFormat, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// 输出格式不支持透明 (JPEG/BMP) 时铺底色的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatteOptions {
+    pub color: [u8; 3],
+    /// 在线性空间按预乘 alpha 混合半透明边缘, 抗锯齿边缘不会发暗或发亮
+    pub premultiplied_edges: bool,
+}
+
+impl Default for MatteOptions {
+    fn default() -> Self {
+        MatteOptions {
+            color: [255, 255, 255],
+            premultiplied_edges: false,
+        }
+    }
+}
+
+pub fn needs_matte(format: ImageFormat) -> bool {
+    matches!(format, ImageFormat::Jpeg | ImageFormat::Bmp)
+}
+
+/// 把透明像素铺到底色上, 没有 alpha 的图片直接转为 RGB
+pub fn flatten(image: DynamicImage, matte: &MatteOptions) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.into_rgb8();
+    }
+
+    let rgba = image.into_rgba8();
+    let mut rgb = RgbImage::new(rgba.width(), rgba.height());
+    let pixels = rgb.par_chunks_mut(3).zip(rgba.par_chunks(4));
+
+    if matte.premultiplied_edges {
+        let to_linear: Vec<f32> = (0..=255)
+            .map(|v| srgb_to_linear(v as f32 / 255.0))
+            .collect();
+        let matte_linear = matte.color.map(|c| to_linear[c as usize]);
+        pixels.for_each(|(output, input)| {
+            let alpha = input[3] as f32 / 255.0;
+            for i in 0..3 {
+                let linear = to_linear[input[i] as usize] * alpha + matte_linear[i] * (1.0 - alpha);
+                output[i] = (linear_to_srgb(linear) * 255.0).round() as u8;
+            }
+        });
+    } else {
+        pixels.for_each(|(output, input)| {
+            let alpha = input[3] as u32;
+            for i in 0..3 {
+                let value = input[i] as u32 * alpha + matte.color[i] as u32 * (255 - alpha);
+                output[i] = ((value + 127) / 255) as u8;
+            }
+        });
+    }
+
+    rgb
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
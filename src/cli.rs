@@ -0,0 +1,145 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::ImageFormatExt;
+use crate::config::Config;
+use crate::convert::{self, ConvertEvent};
+use crate::details;
+use crate::i18n::{self, Language, tr, trf};
+use crate::preset;
+
+const USAGE: &str = "用法: ImgZap --preset <预设名称> <文件或文件夹>...
+       ImgZap --list-presets";
+
+/// 带 `-` 开头的参数时进入命令行模式 (不认识的参数在这里报错),
+/// 其他参数 (如双击打开的文件) 交给窗口
+pub fn is_cli(args: &[String]) -> bool {
+    args.iter().any(|arg| arg.starts_with('-'))
+}
+
+/// 不打开窗口, 按预设转换命令行给出的文件, 返回进程退出码
+///
+/// Windows 的 release 版是 GUI 程序, 输出需要重定向到文件才能看到
+pub fn run(args: &[String]) -> i32 {
+    let config = Config::load();
+    i18n::set_language(config.language.unwrap_or_else(Language::system));
+    let presets = preset::all(&config.presets);
+
+    let mut preset_name = None;
+    let mut inputs = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--preset" => preset_name = args.next(),
+            "--list-presets" => {
+                presets
+                    .iter()
+                    .for_each(|preset| println!("{}\t{}", preset.id, preset.name));
+                return 0;
+            }
+            "-h" | "--help" => {
+                println!("{}", tr(USAGE));
+                return 0;
+            }
+            // 拼错的参数不能当作文件
+            _ if arg.starts_with('-') => {
+                eprintln!("{}", trf("未知的参数 '{}'", &[arg]));
+                eprintln!("{}", tr(USAGE));
+                return 2;
+            }
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+    inputs.retain(|input| {
+        let exists = input.exists();
+        if !exists {
+            eprintln!("{}", trf("找不到 '{}'", &[&input.display()]));
+        }
+        exists
+    });
+
+    let Some(preset_name) = preset_name else {
+        eprintln!("{}", tr(USAGE));
+        return 2;
+    };
+    let Some(preset) = presets.iter().find(|preset| preset.matches(preset_name)) else {
+        let names = presets
+            .iter()
+            .map(|preset| preset.id.as_str())
+            .collect::<Vec<_>>();
+        eprintln!(
+            "{}",
+            trf(
+                "未知的预设 '{}', 可用的预设: {}",
+                &[preset_name, &names.join(", ")]
+            )
+        );
+        return 2;
+    };
+
+    let images = inputs
+        .iter()
+        .flat_map(|input| {
+            WalkDir::new(input)
+                .into_iter()
+                .filter_map(|e| e.ok().filter(|e| e.file_type().is_file()))
+                .map(|entry| entry.into_path())
+        })
+        .filter_map(|path| {
+            let format =
+                tika_magic::from_filepath(&path).and_then(ImageFormatExt::get_format_from_mime)?;
+            Some((path, (format, true)))
+        })
+        .collect::<HashMap<_, _>>();
+    if images.is_empty() {
+        eprintln!("{}", tr("没有找到可转换的图片"));
+        return 1;
+    }
+
+    let convert_img_format = ImageFormatExt::ALL
+        .into_iter()
+        .map(|format| (format, preset.formats.contains(&format)))
+        .collect();
+    let reports = convert::image_to_other(&images, &convert_img_format, &preset.options, |event| {
+        let ConvertEvent::Finished(input_path, reports) = event else {
+            return;
+        };
+        for report in reports {
+            for (output_path, size) in report.outputs.iter() {
+                println!(
+                    "{} -> {} ({})",
+                    input_path.display(),
+                    output_path.display(),
+                    details::format_size(*size)
+                );
+            }
+            if let Some(error) = &report.error {
+                eprintln!(
+                    "{}",
+                    trf(
+                        "{} -> {}: 失败: {}",
+                        &[
+                            &input_path.display(),
+                            &report.convert_format.get_name(),
+                            error
+                        ]
+                    )
+                );
+            }
+        }
+    });
+
+    let failed = reports
+        .iter()
+        .filter(|report| report.error.is_some())
+        .count();
+    println!(
+        "{}",
+        trf(
+            "{} 个成功, {} 个失败",
+            &[&(reports.len() - failed), &failed]
+        )
+    );
+    if failed > 0 { 1 } else { 0 }
+}
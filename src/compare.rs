@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+
+use image::{RgbImage, RgbaImage};
+use rayon::prelude::*;
+
+use crate::ImageFormatExt;
+
+/// 对比视图中显示的图片最长边
+pub const PREVIEW_SIZE: u32 = 1024;
+
+/// 单个文件按当前设置编码后的预览
+#[derive(Debug, Clone)]
+pub struct Preview {
+    pub input_path: PathBuf,
+    pub convert_format: ImageFormatExt,
+    /// 缩小到 PREVIEW_SIZE 以内的原图, 已按输出铺好底色
+    pub source: RgbaImage,
+    /// 无法解码输出时为 None (例如 AVIF)
+    pub encoded: Option<RgbaImage>,
+    pub source_size: u64,
+    pub encoded_size: u64,
+    /// 全尺寸计算, 尺寸不一致或无法解码时为 None
+    pub psnr: Option<f64>,
+    pub ssim: Option<f64>,
+    pub notes: Vec<String>,
+}
+
+/// 峰值信噪比 (dB), 两图相同时为无穷大
+pub fn psnr(a: &RgbImage, b: &RgbImage) -> f64 {
+    let squared_error = a
+        .as_raw()
+        .par_iter()
+        .zip(b.as_raw().par_iter())
+        .map(|(&x, &y)| (x as f64 - y as f64).powi(2))
+        .sum::<f64>();
+    let mse = squared_error / a.as_raw().len() as f64;
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+/// 亮度上的 SSIM, 8x8 窗口, 步长 4
+pub fn ssim(a: &RgbImage, b: &RgbImage) -> f64 {
+    const WINDOW: u32 = 8;
+    const STEP: u32 = 4;
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let (width, height) = a.dimensions();
+    let luma = |image: &RgbImage| {
+        image
+            .pixels()
+            .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+            .collect::<Vec<_>>()
+    };
+    let (luma_a, luma_b) = (luma(a), luma(b));
+
+    // 小于窗口的图片整体作为一个窗口
+    let (window_width, window_height) = (WINDOW.min(width), WINDOW.min(height));
+    let starts = |size: u32, window: u32| (0..=size - window).step_by(STEP as usize);
+    let windows = starts(height, window_height)
+        .flat_map(|y| starts(width, window_width).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+
+    let total = windows
+        .par_iter()
+        .map(|&(x, y)| {
+            let samples = (y..y + window_height).flat_map(|row| {
+                (x..x + window_width).map(move |column| (row * width + column) as usize)
+            });
+            let n = (window_width * window_height) as f64;
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for i in samples {
+                let (va, vb) = (luma_a[i], luma_b[i]);
+                sum_a += va;
+                sum_b += vb;
+                sum_aa += va * va;
+                sum_bb += vb * vb;
+                sum_ab += va * vb;
+            }
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let covariance = sum_ab / n - mean_a * mean_b;
+
+            ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2))
+        })
+        .sum::<f64>();
+
+    total / windows.len() as f64
+}
+
+/// 左侧原图, 右侧编码结果, 在 `split` (0 - 1) 处画一条分隔线
+pub fn split_view(source: &RgbaImage, encoded: &RgbaImage, split: f32) -> RgbaImage {
+    let split_x = (source.width() as f32 * split.clamp(0.0, 1.0)).round() as u32;
+    RgbaImage::from_fn(source.width(), source.height(), |x, y| {
+        if x == split_x {
+            image::Rgba([255, 255, 255, 255])
+        } else if x < split_x {
+            *source.get_pixel(x, y)
+        } else {
+            *encoded.get_pixel(x, y)
+        }
+    })
+}
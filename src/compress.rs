@@ -1 +1,51 @@
+use std::path::Path;
 
+use anyhow::Result;
+use oxipng::{InFile, OutFile, StripChunks, indexset};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::MetadataPolicy;
+
+/// 用 oxipng 无损压缩 PNG 输出
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressOptions {
+    pub enabled: bool,
+    /// 0 - 6, oxipng 的优化等级
+    pub level: u8,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        CompressOptions {
+            enabled: false,
+            level: 2,
+        }
+    }
+}
+
+/// 原地压缩 PNG, 按元数据策略去掉辅助块
+///
+/// 文件时间由转换结束后统一复制, 这里不用保留
+pub fn compress_png(path: &Path, options: &CompressOptions, policy: MetadataPolicy) -> Result<()> {
+    let mut oxipng_options = oxipng::Options::from_preset(options.level);
+    // 写入时 eXIf 和 iCCP 已按策略筛过 (清除时只剩方向标记和像素所在的配置文件),
+    // 这里保留即可
+    oxipng_options.strip = match policy {
+        MetadataPolicy::KeepAll => StripChunks::None,
+        MetadataPolicy::IccAndCopyright => {
+            StripChunks::Keep(indexset! {*b"iCCP", *b"sRGB", *b"gAMA", *b"cHRM", *b"eXIf"})
+        }
+        MetadataPolicy::StripAll => StripChunks::Keep(indexset! {*b"iCCP", *b"eXIf"}),
+    };
+
+    oxipng::optimize(
+        &InFile::Path(path.to_path_buf()),
+        &OutFile::Path {
+            path: None,
+            preserve_attrs: false,
+        },
+        &oxipng_options,
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to compress '{path:?}': {e}"))
+}
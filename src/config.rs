@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::ImageFormatExt;
+use crate::convert::ConvertOptions;
+use crate::i18n::Language;
+use crate::preset::Preset;
+
+/// 最近使用的文件夹最多保留的数量
+const MAX_RECENT_FOLDERS: usize = 10;
+
+/// 保存在配置目录中的设置和上次的文件列表, 启动时读取, 退出时写回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// 勾选的目标格式
+    pub formats: Vec<ImageFormatExt>,
+    /// 格式列表的显示顺序, 由用户调整
+    pub format_order: Vec<ImageFormatExt>,
+    /// 未设置时跟随系统语言
+    pub language: Option<Language>,
+    /// 界面字体, 未设置时按平台自动选择
+    pub ui_font: Option<String>,
+    pub window_size: (f32, f32),
+    /// 最近打开的文件夹, 最新的在前
+    pub recent_folders: Vec<PathBuf>,
+    /// 上次退出时列表中的文件
+    pub last_session: Vec<PathBuf>,
+    pub convert: ConvertOptions,
+    /// 用户保存的预设, 内置预设不写入配置
+    pub presets: Vec<Preset>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            formats: Vec::new(),
+            format_order: ImageFormatExt::ALL.to_vec(),
+            language: None,
+            ui_font: None,
+            window_size: (860.0, 480.0),
+            recent_folders: Vec::new(),
+            last_session: Vec::new(),
+            convert: ConvertOptions::default(),
+            presets: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// 配置文件不存在或无法解析时使用默认设置
+    pub fn load() -> Config {
+        let Some(path) = config_path() else {
+            return Config::default();
+        };
+        if !path.exists() {
+            return Config::default();
+        }
+
+        let mut config: Config = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config '{path:?}'"))
+            .and_then(|text| {
+                toml::from_str(&text).with_context(|| format!("Failed to parse config '{path:?}'"))
+            })
+            .unwrap_or_else(|e| {
+                println!("{e:?}");
+                Config::default()
+            });
+        config.presets.iter_mut().for_each(Preset::fill_id);
+        config.sanitize();
+
+        config
+    }
+
+    /// 配置文件可能被手动编辑, 把超出范围的值改为有效值
+    fn sanitize(&mut self) {
+        let (width, height) = self.window_size;
+        if !(width.is_finite() && height.is_finite() && width >= 1.0 && height >= 1.0) {
+            self.window_size = Config::default().window_size;
+        }
+        self.convert.sanitize();
+        self.presets
+            .iter_mut()
+            .for_each(|preset| preset.options.sanitize());
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = config_path().context("Failed to find config directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create config directory '{dir:?}'"))?;
+        }
+        let text = toml::to_string_pretty(self).context("Failed to serialize config")?;
+        std::fs::write(&path, text).with_context(|| format!("Failed to write config '{path:?}'"))
+    }
+
+    /// 去掉重复的格式, 新版本增加的格式追加到末尾
+    pub fn format_order(&self) -> Vec<ImageFormatExt> {
+        let mut order = Vec::new();
+        for format in self.format_order.iter().chain(ImageFormatExt::ALL.iter()) {
+            if !order.contains(format) {
+                order.push(*format);
+            }
+        }
+
+        order
+    }
+
+    pub fn add_recent_folder(&mut self, folder: PathBuf) {
+        self.recent_folders.retain(|f| *f != folder);
+        self.recent_folders.insert(0, folder);
+        self.recent_folders.truncate(MAX_RECENT_FOLDERS);
+    }
+}
+
+/// Windows: %APPDATA%\ImgZap, macOS: ~/Library/Application Support/ImgZap,
+/// 其他: $XDG_CONFIG_HOME/ImgZap 或 ~/.config/ImgZap
+pub fn config_dir() -> Option<PathBuf> {
+    let env_dir = |name| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME")?.join("Library").join("Application Support")
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| Some(env_dir("HOME")?.join(".config")))?
+    };
+
+    Some(base.join("ImgZap"))
+}
+
+fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::SvgSize;
+
+    #[test]
+    fn sanitize_invalid_values() {
+        let mut config: Config = toml::from_str(
+            r#"
+            window_size = [0.0, 480.0]
+
+            [convert]
+            quality = 0
+
+            [convert.svg]
+            size = { Exact = [0, 256] }
+            sizes = [{ Width = 0 }, { Scale = 2.0 }]
+            "#,
+        )
+        .unwrap();
+        config.sanitize();
+
+        assert_eq!(config.window_size, Config::default().window_size);
+        assert_eq!(config.convert.quality, 1);
+        assert_eq!(config.convert.svg.size, SvgSize::Exact(256, 256));
+        assert_eq!(config.convert.svg.sizes, [SvgSize::Scale(2.0)]);
+    }
+}
@@ -1,66 +1,322 @@
 use crate::ImageFormatExt;
+use crate::alpha::{self, MatteOptions};
+use crate::compare::{self, Preview};
+use crate::compress::{self, CompressOptions};
+use crate::depth;
+use crate::i18n::{tr, trf};
+use crate::icc::{self, ColorOptions, Profile};
+use crate::metadata::{self, Metadata, MetadataPolicy, OrientationMode};
+use crate::resize::ResizeOptions;
+use crate::svg::{self, SvgFit, SvgOptions, SvgSize};
+use crate::tiff::{self, TiffCompression};
+use crate::trace::TraceOptions;
 
 use anyhow::{Context, Result};
+use filetime::FileTime;
 use image::codecs::ico::{IcoEncoder, IcoFrame};
-use image::{DynamicImage, ImageFormat, RgbaImage};
+use image::metadata::Orientation;
+use image::{DynamicImage, ExtendedColorType, ImageDecoder, ImageFormat, ImageReader, RgbaImage};
 use rayon::prelude::*;
-use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
     io::Write,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 use vtracer::ColorImage;
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConvertOptions {
+    pub svg: SvgOptions,
+    pub trace: TraceOptions,
+    /// 编码前缩放, 只作用于位图输出
+    pub resize: ResizeOptions,
+    /// JPEG 等带 EXIF 方向标记的输入如何处理
+    pub orientation: OrientationMode,
+    pub metadata: MetadataPolicy,
+    /// 压缩 PNG 输出, 也用于 PNG -> PNG 重新压缩
+    pub compress: CompressOptions,
+    /// 按嵌入的 ICC 把像素转换到 sRGB 或指定配置文件
+    pub color: ColorOptions,
+    /// 输出沿用输入文件的修改/访问时间
+    pub preserve_timestamps: bool,
+    /// JPEG/BMP 等不支持透明的输出的底色, 也用作应用图标的背景
+    pub matte: MatteOptions,
+    /// 高位深降到 8 位时加有序抖动, 减少色带
+    pub dither: bool,
+    pub tiff: TiffCompression,
+    /// JPEG/AVIF 的编码质量, 1 - 100
+    pub quality: u8,
+    /// 输出文件夹, None 时输出到输入文件旁边
+    pub output_dir: Option<PathBuf>,
+    /// 输出文件名 (不含扩展名), {name} 为输入文件名, {format} 为输出格式的扩展名
+    pub name_template: String,
+}
+
+impl ConvertOptions {
+    /// 把手动编辑或旧版本写入的配置中超出范围的值改为有效值
+    pub fn sanitize(&mut self) {
+        self.quality = self.quality.clamp(1, 100);
+        self.compress.level = self.compress.level.min(6);
+        self.svg.sanitize();
+        self.trace.sanitize();
+    }
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        ConvertOptions {
+            svg: SvgOptions::default(),
+            trace: TraceOptions::default(),
+            resize: ResizeOptions::default(),
+            orientation: OrientationMode::default(),
+            metadata: MetadataPolicy::default(),
+            compress: CompressOptions::default(),
+            color: ColorOptions::default(),
+            preserve_timestamps: false,
+            matte: MatteOptions::default(),
+            dither: false,
+            tiff: TiffCompression::default(),
+            quality: 80,
+            output_dir: None,
+            name_template: "{name}".to_string(),
+        }
+    }
+}
+
+/// 单个文件转换为单个格式的结果
+#[derive(Debug, Clone)]
+pub struct ConvertReport {
+    pub input_path: PathBuf,
+    pub convert_format: ImageFormatExt,
+    /// 写出的文件 (图标集为目录) 及其大小
+    pub outputs: Vec<(PathBuf, u64)>,
+    /// 转换成功但需要提示的问题, 例如 SVG 中缺失的外部资源
+    pub notes: Vec<String>,
+    /// 写入输出的元数据摘要, 不涉及元数据的转换为 None
+    pub metadata: Option<String>,
+    pub error: Option<String>,
+}
+
+/// 一次转换的结果: 写出的文件 (图标集为目录), 提示和元数据摘要
+#[derive(Debug, Default)]
+struct Converted {
+    paths: Vec<PathBuf>,
+    notes: Vec<String>,
+    metadata: Option<String>,
+}
+
+impl Converted {
+    fn written(path: &Path) -> Converted {
+        Converted {
+            paths: vec![path.to_path_buf()],
+            ..Default::default()
+        }
+    }
+}
+
+/// 批量转换的进度, 每个输入文件开始时发送 Started, 所有格式转换完成后发送 Finished
+#[derive(Debug, Clone)]
+pub enum ConvertEvent {
+    Started(PathBuf),
+    Finished(PathBuf, Vec<ConvertReport>),
+}
 
 pub fn image_to_other(
     images: &HashMap<PathBuf, (ImageFormatExt, bool)>,
     convert_img_format: &HashMap<ImageFormatExt, bool>,
-) {
+    options: &ConvertOptions,
+    on_event: impl Fn(ConvertEvent),
+) -> Vec<ConvertReport> {
+    if let Some(output_dir) = &options.output_dir
+        && let Err(e) = std::fs::create_dir_all(output_dir)
+    {
+        println!("Failed to create output folder {output_dir:?}\n{e:?}");
+    }
+
+    // 本次转换已使用的输出路径
+    let mut claimed = HashSet::new();
     images
-        .into_iter()
+        .iter()
         .filter_map(|(p, (f, is_check))| is_check.then_some((p, f)))
-        .for_each(|(input_path, iamge_format)| {
-            convert_img_format
-                .into_iter()
+        .flat_map(|(input_path, iamge_format)| {
+            on_event(ConvertEvent::Started(input_path.clone()));
+            let reports = convert_img_format
+                .iter()
                 .filter_map(|(convert_format, is_convert)| {
-                    (*is_convert && iamge_format.ne(convert_format)).then_some(convert_format)
+                    // SVG -> SVG 即精简, PNG -> PNG 即重新压缩
+                    let same_format = match iamge_format {
+                        ImageFormatExt::Svg => options.svg.optimize.enabled,
+                        ImageFormatExt::Png => options.compress.enabled,
+                        _ => false,
+                    };
+                    (*is_convert && (iamge_format.ne(convert_format) || same_format))
+                        .then_some(convert_format)
                 })
-                .for_each(|convert_format| match iamge_format {
-                    ImageFormatExt::Svg => {
-                        let output_path = input_path.with_extension(convert_format.get_ext());
-                        if svg_to_other(input_path, &output_path, 256, convert_format)
-                            .inspect_err(|e| println!("Failed to svg convert to {convert_format:?}\n{input_path:?}\n{e:?}"))
-                            .is_ok()
-                        {};
-                    }
-                    ImageFormatExt::Ico => {
-                        let output_path = input_path.with_extension(convert_format.get_ext());
-                        if ico_to_other(input_path, &output_path, convert_format)
-                            .inspect_err(|e| println!("Failed to icon convert to {convert_format:?}\n{input_path:?}\n{e:?}"))
-                            .is_ok()
-                        {};
-                    }
-                    _ => {
-                        let output_path = input_path.with_extension(convert_format.get_ext());
-                        if other_to_other(input_path, &output_path, convert_format)
-                            .inspect_err(|e| println!("Failed to convert to {convert_format:?}\n{input_path:?}\n{e:?}"))
-                            .is_ok()
-                        {};
+                .map(|convert_format| {
+                    let planned = output_path(input_path, iamge_format, convert_format, options);
+                    let output_path = unique_path(&planned, &mut claimed);
+                    let result = match iamge_format {
+                        ImageFormatExt::Svg => {
+                            svg_to_other(input_path, &output_path, options, convert_format)
+                        }
+                        ImageFormatExt::Ico => {
+                            ico_to_other(input_path, &output_path, options, convert_format)
+                        }
+                        ImageFormatExt::Tiff => {
+                            tiff_to_other(input_path, &output_path, options, convert_format)
+                        }
+                        _ => other_to_other(input_path, &output_path, options, convert_format),
+                    };
+                    // 只改写本次实际写出的文件, 多尺寸导出和多页 TIFF 为各个带后缀的文件
+                    let result = result.and_then(|converted| {
+                        if options.preserve_timestamps {
+                            for path in &converted.paths {
+                                copy_timestamps(input_path, path)?;
+                            }
+                        }
+                        Ok(converted)
+                    });
+
+                    let (outputs, notes, metadata, error) = match result {
+                        Ok(Converted {
+                            paths,
+                            mut notes,
+                            metadata,
+                        }) => {
+                            if output_path != planned {
+                                notes.push(trf(
+                                    "输出文件名与其他文件重复, 已改为 {}",
+                                    &[&output_path.display()],
+                                ));
+                            }
+                            notes
+                                .iter()
+                                .for_each(|note| println!("{input_path:?}: {note}"));
+                            (output_sizes(paths), notes, metadata, None)
+                        }
+                        Err(e) => {
+                            println!(
+                                "Failed to convert to {convert_format:?}\n{input_path:?}\n{e:?}"
+                            );
+                            (Vec::new(), Vec::new(), None, Some(format!("{e:#}")))
+                        }
+                    };
+
+                    ConvertReport {
+                        input_path: input_path.clone(),
+                        convert_format: *convert_format,
+                        outputs,
+                        notes,
+                        metadata,
+                        error,
                     }
-                });
-        });
+                })
+                .collect::<Vec<_>>();
+            on_event(ConvertEvent::Finished(input_path.clone(), reports.clone()));
+            reports
+        })
+        .collect()
 }
 
-fn ico_to_other(
+/// 写出的文件及其大小, 图标集目录为其中文件的总大小
+fn output_sizes(paths: Vec<PathBuf>) -> Vec<(PathBuf, u64)> {
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let size = if path.is_dir() {
+                WalkDir::new(&path)
+                    .into_iter()
+                    .filter_map(|e| e.ok()?.metadata().ok())
+                    .filter(|metadata| metadata.is_file())
+                    .map(|metadata| metadata.len())
+                    .sum()
+            } else {
+                std::fs::metadata(&path).ok()?.len()
+            };
+            Some((path, size))
+        })
+        .collect()
+}
+
+fn output_path(
     input_path: &Path,
-    output_path: &Path,
+    iamge_format: &ImageFormatExt,
     convert_format: &ImageFormatExt,
-) -> Result<()> {
+    options: &ConvertOptions,
+) -> PathBuf {
+    let stem = input_path
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or("image");
+    // 模板中的路径分隔符和 .. 会把文件写到输出文件夹之外
+    let name = options
+        .name_template
+        .replace(['/', '\\'], "_")
+        .replace("..", "_")
+        .replace("{name}", stem)
+        .replace("{format}", &convert_format.get_ext());
+    // 模板为空时沿用输入文件名
+    let name = if name.trim().is_empty() { stem } else { &name };
+    let file_name = match convert_format {
+        // 同格式输出 (精简 SVG, 重新压缩 PNG): icon.svg -> icon.min.svg
+        _ if iamge_format == convert_format => {
+            format!("{name}.min.{}", convert_format.get_ext())
+        }
+        // 图标集是一个目录: icon.svg -> icon_AppIcon/
+        ImageFormatExt::AppIcon => format!("{name}_AppIcon"),
+        _ => format!("{name}.{}", convert_format.get_ext()),
+    };
+
+    match &options.output_dir {
+        Some(output_dir) => output_dir.join(file_name),
+        None => input_path.with_file_name(file_name),
+    }
+}
+
+/// 路径已被本次转换的其他输出使用时加上 " (2)" 等序号, 避免互相覆盖
+///
+/// 例如输出到同一文件夹的 a/icon.png 和 b/icon.png, 或不含 {name} 的命名模板
+fn unique_path(path: &Path, claimed: &mut HashSet<PathBuf>) -> PathBuf {
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("image");
+    let mut unique = path.to_path_buf();
+    let mut number = 2;
+    while claimed.contains(&unique) {
+        unique = match path.extension().and_then(OsStr::to_str) {
+            Some(ext) => path.with_file_name(format!("{stem} ({number}).{ext}")),
+            None => path.with_file_name(format!("{stem} ({number})")),
+        };
+        number += 1;
+    }
+    claimed.insert(unique.clone());
+
+    unique
+}
+
+/// 把输入文件的修改/访问时间复制到输出, 输出为目录 (图标集) 时复制到其中每个文件
+fn copy_timestamps(input_path: &Path, output_path: &Path) -> Result<()> {
+    let input_metadata = std::fs::metadata(input_path)
+        .with_context(|| format!("Failed to read metadata of '{input_path:?}'"))?;
+    let modified = FileTime::from_last_modification_time(&input_metadata);
+    let accessed = FileTime::from_last_access_time(&input_metadata);
+
+    WalkDir::new(output_path).into_iter().try_for_each(|entry| {
+        let entry = entry?;
+        filetime::set_file_times(entry.path(), accessed, modified)
+            .with_context(|| format!("Failed to set timestamps of '{:?}'", entry.path()))
+    })
+}
+
+fn decode_ico(input_path: &Path) -> Result<RgbaImage> {
     let file = std::fs::File::open(input_path)?;
     let icon_dir = ico::IconDir::read(file)?;
     let largest_entry = icon_dir
         .entries()
-        .into_iter()
+        .iter()
         .max_by_key(|entry| entry.width() * entry.height())
         .ok_or(anyhow::anyhow!(
             "No images found in ICO file: {input_path:?}"
@@ -68,159 +324,714 @@ fn ico_to_other(
 
     let ico_image = largest_entry.decode()?;
 
-    match convert_format.get_format() {
-        Some(f) => {
-            let output_file = std::fs::File::create(output_path)?;
-            let mut writer = std::io::BufWriter::new(output_file);
-
-            if f == ImageFormat::Jpeg {
-                let rgba_image = RgbaImage::from_raw(
-                    ico_image.width() as u32,
-                    ico_image.height() as u32,
-                    ico_image.rgba_data().to_vec(),
-                )
-                .ok_or(anyhow::anyhow!(
-                    "Failed to create RGBA image: {input_path:?}"
-                ))?;
-
-                let rgb_image = DynamicImage::ImageRgba8(rgba_image).to_rgb8();
-                rgb_image.save_with_format(
-                    output_path,
-                    convert_format
-                        .get_format()
-                        .expect("No supported image formats"),
+    RgbaImage::from_raw(
+        ico_image.width(),
+        ico_image.height(),
+        ico_image.rgba_data().to_vec(),
+    )
+    .ok_or(anyhow::anyhow!(
+        "Failed to create RGBA image: {input_path:?}"
+    ))
+}
+
+/// 列表中的缩略图和原图尺寸, SVG/ICO 与转换使用同样的解码路径
+pub fn thumbnail(
+    input_path: &Path,
+    image_format: &ImageFormatExt,
+    size: u32,
+) -> Result<(RgbaImage, (u32, u32))> {
+    let image: DynamicImage = match image_format {
+        ImageFormatExt::Svg => {
+            let svg_options = SvgOptions {
+                size: SvgSize::Exact(size, size),
+                fit: SvgFit::Contain,
+                ..Default::default()
+            };
+            let (rtree, _) = svg::load_svg(input_path, &svg_options)?;
+            let dimensions = (
+                rtree.size().width().round() as u32,
+                rtree.size().height().round() as u32,
+            );
+            return Ok((svg::render_svg(&rtree, &svg_options)?, dimensions));
+        }
+        ImageFormatExt::Ico => decode_ico(input_path)?.into(),
+        _ => {
+            let (mut image, orientation, _) = decode_image(input_path)?;
+            image.apply_orientation(orientation);
+            image
+        }
+    };
+
+    let dimensions = (image.width(), image.height());
+    Ok((image.thumbnail(size, size).to_rgba8(), dimensions))
+}
+
+/// 按当前设置把单个文件编码到临时文件, 与原图对比大小和画质
+pub fn preview(
+    input_path: &Path,
+    image_format: &ImageFormatExt,
+    convert_format: &ImageFormatExt,
+    options: &ConvertOptions,
+) -> Result<Preview> {
+    static PREVIEW_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    anyhow::ensure!(
+        convert_format.get_format().is_some(),
+        "Cannot preview {convert_format:?}"
+    );
+    // 多尺寸导出只预览主尺寸
+    let options = ConvertOptions {
+        svg: SvgOptions {
+            sizes: Vec::new(),
+            ..options.svg.clone()
+        },
+        preserve_timestamps: false,
+        ..options.clone()
+    };
+    let output_path = std::env::temp_dir().join(format!(
+        "imgzap-preview-{}-{}.{}",
+        std::process::id(),
+        PREVIEW_COUNT.fetch_add(1, Ordering::Relaxed),
+        convert_format.get_ext()
+    ));
+
+    // 与批量转换走同样的流程, 多页 TIFF 只预览第一页; SVG 只栅格化一次, 同时作为原图
+    let (result, source) = match image_format {
+        ImageFormatExt::Svg => {
+            let (rtree, unresolved) = svg::load_svg(input_path, &options.svg)?;
+            let image = svg::render_svg(&rtree, &options.svg)?;
+            let result = save_svg_image(
+                image.clone(),
+                unresolved,
+                &output_path,
+                &options,
+                convert_format,
+            );
+            (result, Ok(image.into()))
+        }
+        ImageFormatExt::Ico => (
+            ico_to_other(input_path, &output_path, &options, convert_format),
+            decode_ico(input_path).map(DynamicImage::from),
+        ),
+        // 原图也做同样的颜色转换, 画质指标只反映编码损失
+        _ => (
+            other_to_other(input_path, &output_path, &options, convert_format),
+            decode_image(input_path).and_then(|(image, orientation, mut metadata)| {
+                let (mut image, _) = convert_color(
+                    image,
+                    &mut metadata,
+                    &options,
+                    convert_format,
+                    &mut Vec::new(),
                 )?;
-            } else {
-                let rgba_image = RgbaImage::from_raw(
-                    ico_image.width() as u32,
-                    ico_image.height() as u32,
-                    ico_image.rgba_data().to_vec(),
-                )
-                .ok_or(anyhow::anyhow!(
-                    "Failed to create RGBA image: {input_path:?}"
-                ))?;
+                image.apply_orientation(orientation);
+                Ok(image)
+            }),
+        ),
+    };
+    let result = result.and_then(|Converted { notes, .. }| {
+        let encoded_size = std::fs::metadata(&output_path)?.len();
+        let encoded = decode_image(&output_path).map(|(mut image, orientation, _)| {
+            image.apply_orientation(orientation);
+            image
+        });
+        Ok((notes, encoded_size, encoded))
+    });
+    let _ = std::fs::remove_file(&output_path);
+    let (mut notes, encoded_size, encoded) = result?;
+    if *convert_format == ImageFormatExt::WebP {
+        notes.push(tr("WEBP 总是无损编码, 质量设置对它不起作用").to_string());
+    }
 
-                rgba_image.write_to(&mut writer, f)?
+    let source = options.resize.apply(source?);
+
+    // 两边都铺上同样的底色, 透明区域不计入误差
+    let source_rgb = alpha::flatten(source.clone(), &options.matte);
+    let (psnr, ssim) = match &encoded {
+        Ok(encoded) => {
+            let encoded_rgb = alpha::flatten(encoded.clone(), &options.matte);
+            if encoded_rgb.dimensions() == source_rgb.dimensions() {
+                (
+                    Some(compare::psnr(&source_rgb, &encoded_rgb)),
+                    Some(compare::ssim(&source_rgb, &encoded_rgb)),
+                )
+            } else {
+                notes.push(tr("输出尺寸与原图不同, 无法计算画质指标").to_string());
+                (None, None)
             }
         }
-        None => {
-            let svg_file = vtracer::convert(
-                ColorImage {
-                    pixels: ico_image.rgba_data().to_vec(),
-                    width: ico_image.width() as usize,
-                    height: ico_image.height() as usize,
-                },
-                vtracer::Config::default(),
+        Err(e) => {
+            notes.push(trf(
+                "无法解码 {} 预览: {}",
+                &[&convert_format.get_name(), &e],
+            ));
+            (None, None)
+        }
+    };
+
+    // 只缩小, 不放大
+    let source = DynamicImage::ImageRgb8(source_rgb);
+    let source = if source.width().max(source.height()) > compare::PREVIEW_SIZE {
+        source.thumbnail(compare::PREVIEW_SIZE, compare::PREVIEW_SIZE)
+    } else {
+        source
+    }
+    .to_rgba8();
+    let encoded = encoded.ok().map(|encoded| {
+        DynamicImage::ImageRgb8(alpha::flatten(encoded, &options.matte))
+            .resize_exact(
+                source.width(),
+                source.height(),
+                image::imageops::FilterType::Triangle,
             )
-            .map_err(|e| anyhow::anyhow!("{e}"))?;
+            .to_rgba8()
+    });
+
+    Ok(Preview {
+        input_path: input_path.to_path_buf(),
+        convert_format: *convert_format,
+        source,
+        encoded,
+        source_size: std::fs::metadata(input_path)?.len(),
+        encoded_size,
+        psnr,
+        ssim,
+        notes,
+    })
+}
 
-            let mut output_file = std::fs::File::create(output_path)?;
-            write!(&mut output_file, "{}", svg_file).with_context(|| "Failed to write file.")?;
+fn ico_to_other(
+    input_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+    convert_format: &ImageFormatExt,
+) -> Result<Converted> {
+    let rgba_image = decode_ico(input_path)?;
+
+    let notes = match convert_format.get_format() {
+        Some(format) => save_image(
+            rgba_image.into(),
+            output_path,
+            options,
+            format,
+            &Metadata::default(),
+        )?,
+        None if *convert_format == ImageFormatExt::AppIcon => {
+            other_to_app_icon(rgba_image.into(), output_path, &options.matte)?;
+            Vec::new()
         }
-    }
+        None => {
+            other_to_svg(rgba_image, output_path, options)?;
+            Vec::new()
+        }
+    };
 
-    Ok(())
+    Ok(Converted {
+        notes,
+        ..Converted::written(output_path)
+    })
 }
 
 fn svg_to_other(
     input_path: &Path,
     output_path: &Path,
-    size: u32,
+    options: &ConvertOptions,
     convert_format: &ImageFormatExt,
-) -> Result<()> {
-    let mut fontdb = usvg::fontdb::Database::new();
-    fontdb.load_system_fonts();
+) -> Result<Converted> {
+    let svg_options = &options.svg;
+    if *convert_format == ImageFormatExt::Svg {
+        return Ok(Converted {
+            notes: optimize_svg_file(input_path, output_path, svg_options)?,
+            ..Converted::written(output_path)
+        });
+    }
 
-    let opt = usvg::Options {
-        resources_dir: Some(input_path.into()),
-        fontdb: fontdb.into(),
-        ..Default::default()
-    };
+    let (rtree, unresolved) = svg::load_svg(input_path, svg_options)?;
 
-    let svg_data = std::fs::read(&input_path)
-        .with_context(|| format!("Failed to read file '{input_path:?}'"))?;
-    let rtree =
-        usvg::Tree::from_data(&svg_data, &opt).with_context(|| "Failed to parse SVG contents")?;
+    // 多尺寸导出只对位图格式生效, 每个尺寸输出一个带后缀的文件: icon_24.png, icon@2x.png
+    if !svg_options.sizes.is_empty() && convert_format.get_format().is_some() {
+        let saved = svg_options
+            .sizes
+            .par_iter()
+            .map(|size| -> Result<(PathBuf, Vec<String>)> {
+                let image = svg::render_svg(
+                    &rtree,
+                    &SvgOptions {
+                        size: *size,
+                        ..svg_options.clone()
+                    },
+                )?;
+                let output_path = sized_path(output_path, size, convert_format);
+                let notes = save_rgba(image, &output_path, options, convert_format)?;
+                Ok((output_path, notes))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-    let mut pixmap = tiny_skia::Pixmap::new(size, size)
-        .ok_or_else(|| anyhow::anyhow!("Failed to create SVG Pixmap!"))?;
-    let pixmap_size = rtree.size();
+        let mut notes = unresolved;
+        let mut paths = Vec::with_capacity(saved.len());
+        for (path, saved_notes) in saved {
+            paths.push(path);
+            // 各尺寸的提示相同, 只保留一份
+            saved_notes.into_iter().for_each(|note| {
+                if !notes.contains(&note) {
+                    notes.push(note);
+                }
+            });
+        }
+        return Ok(Converted {
+            paths,
+            notes,
+            metadata: None,
+        });
+    }
 
-    let transform = tiny_skia::Transform::from_scale(
-        size as f32 / pixmap_size.width(),
-        size as f32 / pixmap_size.height(),
-    );
-    resvg::render(&rtree, transform, &mut pixmap.as_mut());
-
-    let mut image = RgbaImage::new(size, size);
-    let buffer = image.as_mut();
-    buffer.par_chunks_mut(4).enumerate().for_each(|(i, chunk)| {
-        let x = (i as u32) % size;
-        let y = (i as u32) / size;
-
-        let pixel = pixmap
-            .pixel(x, y)
-            .unwrap_or(tiny_skia::PremultipliedColorU8::TRANSPARENT);
-
-        chunk[0] = pixel.red();
-        chunk[1] = pixel.green();
-        chunk[2] = pixel.blue();
-        chunk[3] = pixel.alpha();
-    });
+    // 图标集以最大尺寸 (1024) 栅格化一次, 再缩放到各个尺寸
+    let image = match convert_format {
+        ImageFormatExt::AppIcon => svg::render_svg(
+            &rtree,
+            &SvgOptions {
+                size: SvgSize::Exact(APP_ICON_MASTER_SIZE, APP_ICON_MASTER_SIZE),
+                fit: SvgFit::Contain,
+                ..Default::default()
+            },
+        )?,
+        _ => svg::render_svg(&rtree, svg_options)?,
+    };
+
+    save_svg_image(image, unresolved, output_path, options, convert_format)
+}
 
+/// 保存栅格化后的 SVG, `notes` 为解析时的提示
+fn save_svg_image(
+    image: RgbaImage,
+    mut notes: Vec<String>,
+    output_path: &Path,
+    options: &ConvertOptions,
+    convert_format: &ImageFormatExt,
+) -> Result<Converted> {
     match convert_format {
         ImageFormatExt::Ico => {
-            other_to_icon(image.into(), &output_path, vec![16, 32, 48, 64, 128, 256])?
+            other_to_icon(image.into(), output_path, vec![16, 32, 48, 64, 128, 256])?
         }
-        ImageFormatExt::Jpeg => {
-            let image = DynamicImage::ImageRgba8(image).to_rgb8();
-            image.save_with_format(
-                output_path,
-                convert_format
-                    .get_format()
-                    .expect("No supported image formats"),
-            )?
-        }
-        _ => image.save_with_format(
+        ImageFormatExt::AppIcon => other_to_app_icon(image.into(), output_path, &options.matte)?,
+        _ => notes.extend(save_rgba(image, output_path, options, convert_format)?),
+    }
+    Ok(Converted {
+        notes,
+        ..Converted::written(output_path)
+    })
+}
+
+fn optimize_svg_file(
+    input_path: &Path,
+    output_path: &Path,
+    options: &SvgOptions,
+) -> Result<Vec<String>> {
+    let (rtree, unresolved) = svg::load_svg(input_path, options)?;
+    std::fs::write(output_path, svg::optimize_svg(&rtree, &options.optimize))
+        .with_context(|| format!("Failed to write file '{output_path:?}'"))?;
+
+    Ok(unresolved)
+}
+
+fn save_rgba(
+    image: RgbaImage,
+    output_path: &Path,
+    options: &ConvertOptions,
+    convert_format: &ImageFormatExt,
+) -> Result<Vec<String>> {
+    let format = convert_format
+        .get_format()
+        .expect("No supported image formats");
+    save_image(
+        image.into(),
+        output_path,
+        options,
+        format,
+        &Metadata::default(),
+    )
+}
+
+/// 位图输出的统一出口: 缩放, 调整位深, 铺底色, 编码并写入元数据, PNG 再交给压缩阶段
+///
+/// 返回精度损失等提示
+fn save_image(
+    image: DynamicImage,
+    output_path: &Path,
+    options: &ConvertOptions,
+    format: ImageFormat,
+    metadata: &Metadata,
+) -> Result<Vec<String>> {
+    let image = options.resize.apply(image);
+    let (image, note) = depth::fit(image, format, options.dither);
+    let image = if alpha::needs_matte(format) {
+        DynamicImage::ImageRgb8(alpha::flatten(image, &options.matte))
+    } else {
+        image
+    };
+    let mut notes = Vec::from_iter(note);
+    if format == ImageFormat::Tiff {
+        tiff::save_tiff(&[image], output_path, options.tiff)?;
+    } else {
+        notes.extend(metadata::save_with_metadata(
+            &image,
             output_path,
-            convert_format
-                .get_format()
-                .expect("No supported image formats"),
-        )?,
+            format,
+            metadata,
+            options.quality,
+        )?);
+    }
+
+    if format == ImageFormat::Png && options.compress.enabled {
+        compress::compress_png(output_path, &options.compress, options.metadata)?;
+    }
+
+    Ok(notes)
+}
+
+/// 打开位图, 读出 EXIF 方向 (没有时为 NoTransforms) 和元数据, 像素留给调用方解码
+pub fn open_image(input_path: &Path) -> Result<(impl ImageDecoder, Orientation, Metadata)> {
+    let mut decoder = ImageReader::open(input_path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let metadata = Metadata::read(&mut decoder, input_path)?;
+    let orientation = match orientation {
+        Orientation::NoTransforms => metadata.orientation(),
+        orientation => orientation,
+    };
+
+    Ok((decoder, orientation, metadata))
+}
+
+fn decode_image(input_path: &Path) -> Result<(DynamicImage, Orientation, Metadata)> {
+    let (decoder, orientation, metadata) = open_image(input_path)?;
+    let image = DynamicImage::from_decoder(decoder)?;
+
+    Ok((image, orientation, metadata))
+}
+
+/// 多页 TIFF 每页输出一个带序号的文件: scan.tiff -> scan_1.png, scan_2.png
+fn tiff_to_other(
+    input_path: &Path,
+    output_path: &Path,
+    options: &ConvertOptions,
+    convert_format: &ImageFormatExt,
+) -> Result<Converted> {
+    // 方向和元数据取自第一页, 只读文件头; image 不支持的页面 (调色板等) 没有元数据
+    let (orientation, metadata) = open_image(input_path)
+        .map(|(_, orientation, metadata)| (orientation, metadata))
+        .unwrap_or_else(|_| (Orientation::NoTransforms, Metadata::default()));
+    let (pages, skipped) = tiff::read_pages(input_path)?;
+    let page_count = pages.len() + skipped.len();
+    if page_count == 1 {
+        let page = pages.into_iter().next().context("No TIFF page")?;
+        return convert_image(
+            page,
+            orientation,
+            metadata,
+            output_path,
+            options,
+            convert_format,
+        );
+    }
+
+    let mut converted = Converted {
+        notes: vec![trf("共 {} 页, 已拆分为带序号的文件", &[&page_count])],
+        ..Default::default()
+    };
+    converted.notes.extend(skipped);
+    for (index, page) in pages.into_iter().enumerate() {
+        let output_path = numbered_path(output_path, index + 1);
+        let page_converted = convert_image(
+            page,
+            orientation,
+            metadata.clone(),
+            &output_path,
+            options,
+            convert_format,
+        )?;
+        // 各页的提示基本相同, 只保留一份
+        page_converted.notes.into_iter().for_each(|note| {
+            if !converted.notes.contains(&note) {
+                converted.notes.push(note);
+            }
+        });
+        converted.paths.extend(page_converted.paths);
+        converted.metadata = converted.metadata.or(page_converted.metadata);
+    }
+
+    Ok(converted)
+}
+
+/// 按给定顺序把图片合并为一个多页 TIFF, 多页 TIFF 输入的每一页都会加入
+pub fn merge_to_tiff(
+    inputs: &[(PathBuf, ImageFormatExt)],
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> ConvertReport {
+    let (outputs, notes, error) = match merge_pages(inputs, output_path, options) {
+        Ok(notes) => {
+            let outputs = std::fs::metadata(output_path)
+                .map(|metadata| vec![(output_path.to_path_buf(), metadata.len())])
+                .unwrap_or_default();
+            (outputs, notes, None)
+        }
+        Err(e) => {
+            println!("Failed to merge into {output_path:?}\n{e:?}");
+            (Vec::new(), Vec::new(), Some(format!("{e:#}")))
+        }
+    };
+    ConvertReport {
+        input_path: output_path.to_path_buf(),
+        convert_format: ImageFormatExt::Tiff,
+        outputs,
+        notes,
+        metadata: None,
+        error,
+    }
+}
+
+fn merge_pages(
+    inputs: &[(PathBuf, ImageFormatExt)],
+    output_path: &Path,
+    options: &ConvertOptions,
+) -> Result<Vec<String>> {
+    let mut notes = Vec::new();
+    let mut pages = Vec::new();
+    for (input_path, image_format) in inputs {
+        let input_pages = match image_format {
+            ImageFormatExt::Svg => {
+                let (rtree, unresolved) = svg::load_svg(input_path, &options.svg)?;
+                notes.extend(unresolved);
+                vec![svg::render_svg(&rtree, &options.svg)?.into()]
+            }
+            ImageFormatExt::Ico => vec![decode_ico(input_path)?.into()],
+            ImageFormatExt::Tiff => {
+                let (pages, skipped) = tiff::read_pages(input_path)?;
+                notes.extend(skipped);
+                pages
+            }
+            _ => {
+                let (mut image, orientation, _) = decode_image(input_path)?;
+                image.apply_orientation(orientation);
+                vec![image]
+            }
+        };
+        for page in input_pages {
+            let (page, note) = depth::fit(
+                options.resize.apply(page),
+                ImageFormat::Tiff,
+                options.dither,
+            );
+            if let Some(note) = note
+                && !notes.contains(&note)
+            {
+                notes.push(note);
+            }
+            pages.push(page);
+        }
+    }
+    anyhow::ensure!(!pages.is_empty(), "No images selected");
+
+    tiff::save_tiff(&pages, output_path, options.tiff)?;
+    notes.insert(
+        0,
+        trf("已合并 {} 个文件, 共 {} 页", &[&inputs.len(), &pages.len()]),
+    );
+    Ok(notes)
+}
+
+/// 多尺寸导出的文件名: icon.png -> icon_48.png
+fn sized_path(path: &Path, size: &SvgSize, convert_format: &ImageFormatExt) -> PathBuf {
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("image");
+    path.with_file_name(format!(
+        "{stem}{}.{}",
+        size.suffix(),
+        convert_format.get_ext()
+    ))
+}
+
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("image");
+    match path.extension().and_then(OsStr::to_str) {
+        Some(ext) => path.with_file_name(format!("{stem}_{number}.{ext}")),
+        None => path.with_file_name(format!("{stem}_{number}")),
     }
-    Ok(())
 }
 
 fn other_to_other(
     input_path: &Path,
     output_path: &Path,
+    options: &ConvertOptions,
+    convert_format: &ImageFormatExt,
+) -> Result<Converted> {
+    let (image, orientation, metadata) = decode_image(input_path)?;
+    convert_image(
+        image,
+        orientation,
+        metadata,
+        output_path,
+        options,
+        convert_format,
+    )
+}
+
+/// 按颜色设置转换像素并更新 `metadata.icc`, 没有嵌入配置文件的输入按 sRGB 处理
+///
+/// 转换到指定配置文件时同时返回它, 输出必须带上它, 否则会被当作 sRGB 显示
+fn convert_color(
+    mut image: DynamicImage,
+    metadata: &mut Metadata,
+    options: &ConvertOptions,
+    convert_format: &ImageFormatExt,
+    notes: &mut Vec<String>,
+) -> Result<(DynamicImage, Option<Vec<u8>>)> {
+    let Some((mut target, mut target_icc)) = options.color.target_profile()? else {
+        return Ok((image, None));
+    };
+    if target_icc.is_some()
+        && !convert_format
+            .get_format()
+            .is_some_and(metadata::supports_metadata)
+    {
+        notes.push(trf(
+            "{} 无法嵌入 ICC 配置文件, 已转换为 sRGB",
+            &[&convert_format.get_name()],
+        ));
+        (target, target_icc) = (Profile::srgb(), None);
+    }
+
+    match metadata
+        .icc
+        .as_deref()
+        .map_or(Ok(Profile::srgb()), Profile::parse)
+    {
+        Ok(source) => {
+            // 未标记的图片转 sRGB 或配置文件相同时像素不变, 避免往返查表的误差
+            if !source.same_as(&target) {
+                let is_luma = image.color().channel_count() <= 2;
+                image = icc::transform(image, &source, &target);
+                // 嵌入 RGB 配置文件的输出必须是彩色, sRGB 不嵌入, 可以还原为灰度
+                if is_luma && target_icc.is_none() {
+                    image = icc::to_luma(image);
+                }
+            }
+            metadata.icc = target_icc.clone();
+            Ok((image, target_icc))
+        }
+        Err(e) => {
+            notes.push(trf("无法转换颜色, 已保留原 ICC 配置文件: {}", &[&e]));
+            Ok((image, None))
+        }
+    }
+}
+
+/// 颜色转换, 方向, 元数据筛选, 然后按目标格式输出
+fn convert_image(
+    image: DynamicImage,
+    orientation: Orientation,
+    mut metadata: Metadata,
+    output_path: &Path,
+    options: &ConvertOptions,
     convert_format: &ImageFormatExt,
-) -> Result<()> {
-    let image = image::open(input_path)?;
+) -> Result<Converted> {
+    let mut notes = Vec::new();
+    let embeds_metadata = convert_format
+        .get_format()
+        .is_some_and(metadata::supports_metadata);
+    let (mut image, converted_icc) =
+        convert_color(image, &mut metadata, options, convert_format, &mut notes)?;
+
+    // 保留方向标记时像素不动, 编码后把标记写回; 写不了的格式只能旋转像素
+    let keep_orientation = orientation != Orientation::NoTransforms
+        && options.orientation == OrientationMode::Preserve
+        && embeds_metadata;
+    if !keep_orientation {
+        if orientation != Orientation::NoTransforms
+            && options.orientation == OrientationMode::Preserve
+        {
+            notes.push(trf(
+                "{} 无法保存方向标记, 已按 EXIF 方向旋转",
+                &[&convert_format.get_name()],
+            ));
+        }
+        image.apply_orientation(orientation);
+    }
+
     match convert_format.get_format() {
         Some(format) => {
-            if format == ImageFormat::Jpeg {
-                let image = image.to_rgb8();
-                image.save_with_format(output_path, format)?
+            let orientation = if keep_orientation {
+                orientation
             } else {
-                image.save_with_format(output_path, format)?
+                Orientation::NoTransforms
+            };
+            let mut metadata = metadata.filter(options.metadata, orientation);
+            // 不受元数据策略影响
+            if converted_icc.is_some() {
+                metadata.icc = converted_icc;
             }
+            if !metadata::supports_metadata(format) && !metadata.is_empty() {
+                notes.push(trf(
+                    "{} 无法保存元数据, 已丢弃 {}",
+                    &[&convert_format.get_name(), &metadata.summary()],
+                ));
+                metadata = Metadata::default();
+            }
+
+            notes.extend(save_image(image, output_path, options, format, &metadata)?);
+            return Ok(Converted {
+                notes,
+                metadata: Some(metadata.summary()),
+                ..Converted::written(output_path)
+            });
         }
         None => {
+            // 图标和 SVG 只能是 8 位
+            let depth = depth::bit_depth(image.color());
+            if depth > 8 {
+                image = depth::to_8bit(&image, options.dither);
+                notes.push(depth::reduced_note(
+                    convert_format.get_name(),
+                    depth,
+                    options.dither,
+                ));
+            }
             if *convert_format == ImageFormatExt::Ico {
                 other_to_icon(image, output_path, vec![16, 32, 48, 64, 128, 256])?;
+            } else if *convert_format == ImageFormatExt::AppIcon {
+                other_to_app_icon(image, output_path, &options.matte)?;
             } else if *convert_format == ImageFormatExt::Svg {
-                other_to_svg(input_path, output_path, vtracer::Config::default())?
+                other_to_svg(image.to_rgba8(), output_path, options)?;
             }
         }
     }
 
-    Ok(())
+    Ok(Converted {
+        notes,
+        ..Converted::written(output_path)
+    })
 }
 
-fn other_to_svg(input_path: &Path, output_path: &Path, config: vtracer::Config) -> Result<()> {
-    vtracer::convert_image_to_svg(input_path, output_path, config)
-        .map_err(|e| anyhow::anyhow!("Failed to convert to svg: {e}\n{input_path:?}\n"))?;
+fn other_to_svg(image: RgbaImage, output_path: &Path, options: &ConvertOptions) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let svg_file = vtracer::convert(
+        ColorImage {
+            pixels: image.into_raw(),
+            width: width as usize,
+            height: height as usize,
+        },
+        options.trace.to_config(),
+    )
+    .map_err(|e| anyhow::anyhow!("Failed to convert to svg: {e}"))?;
+
+    let mut output_file = std::fs::File::create(output_path)?;
+    write!(&mut output_file, "{}", svg_file).with_context(|| "Failed to write file.")?;
+    drop(output_file);
+
+    if options.svg.optimize.enabled {
+        optimize_svg_file(output_path, output_path, &options.svg)?;
+    }
 
     Ok(())
 }
@@ -233,7 +1044,7 @@ fn other_to_icon(image: DynamicImage, output_path: &Path, sizes: Vec<u32>) -> Re
         .map(|&sz| {
             let resized_image = image.resize_exact(sz, sz, filter);
             let rgba = resized_image.to_rgba8();
-            IcoFrame::as_png(&rgba, sz, sz, image.color().into())
+            IcoFrame::as_png(&rgba, sz, sz, ExtendedColorType::Rgba8)
                 .with_context(|| "Failed to encode frame")
         })
         .collect::<Result<Vec<IcoFrame>>>()?;
@@ -247,3 +1058,169 @@ fn other_to_icon(image: DynamicImage, output_path: &Path, sizes: Vec<u32>) -> Re
 
     Ok(())
 }
+
+const APP_ICON_MASTER_SIZE: u32 = 1024;
+
+// (目录, 传统图标尺寸, 自适应图标前景/背景尺寸)
+const ANDROID_MIPMAPS: [(&str, u32, u32); 5] = [
+    ("mipmap-mdpi", 48, 108),
+    ("mipmap-hdpi", 72, 162),
+    ("mipmap-xhdpi", 96, 216),
+    ("mipmap-xxhdpi", 144, 324),
+    ("mipmap-xxxhdpi", 192, 432),
+];
+
+// (idiom, 逻辑尺寸, 倍率)
+const IOS_APP_ICONS: [(&str, &str, u32); 18] = [
+    ("iphone", "20", 2),
+    ("iphone", "20", 3),
+    ("iphone", "29", 2),
+    ("iphone", "29", 3),
+    ("iphone", "40", 2),
+    ("iphone", "40", 3),
+    ("iphone", "60", 2),
+    ("iphone", "60", 3),
+    ("ipad", "20", 1),
+    ("ipad", "20", 2),
+    ("ipad", "29", 1),
+    ("ipad", "29", 2),
+    ("ipad", "40", 1),
+    ("ipad", "40", 2),
+    ("ipad", "76", 1),
+    ("ipad", "76", 2),
+    ("ipad", "83.5", 2),
+    ("ios-marketing", "1024", 1),
+];
+
+const ADAPTIVE_ICON_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<adaptive-icon xmlns:android="http://schemas.android.com/apk/res/android">
+    <background android:drawable="@mipmap/ic_launcher_background" />
+    <foreground android:drawable="@mipmap/ic_launcher_foreground" />
+</adaptive-icon>
+"#;
+
+/// 等比缩放到 `size` 以内, 居中放到 `canvas` x `canvas` 的透明画布上
+fn fit_square(image: &DynamicImage, size: u32, canvas: u32) -> RgbaImage {
+    let resized = image
+        .resize(size, size, image::imageops::FilterType::Lanczos3)
+        .to_rgba8();
+    let mut square = RgbaImage::new(canvas, canvas);
+    let x = (canvas - resized.width()) / 2;
+    let y = (canvas - resized.height()) / 2;
+    image::imageops::overlay(&mut square, &resized, x.into(), y.into());
+    square
+}
+
+/// 自适应图标的背景层和 iOS 图标的底色使用透明底色设置
+fn other_to_app_icon(image: DynamicImage, output_dir: &Path, matte: &MatteOptions) -> Result<()> {
+    let android_dir = output_dir.join("android");
+    let ios_dir = output_dir.join("ios").join("AppIcon.appiconset");
+
+    ANDROID_MIPMAPS.par_iter().try_for_each(
+        |&(mipmap, legacy_size, adaptive_size)| -> Result<()> {
+            let dir = android_dir.join(mipmap);
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create directory '{dir:?}'"))?;
+
+            fit_square(&image, legacy_size, legacy_size).save(dir.join("ic_launcher.png"))?;
+
+            // 自适应图标: 108dp 画布, 内容位于中间 72dp 安全区
+            let safe_zone = adaptive_size * 72 / 108;
+            fit_square(&image, safe_zone, adaptive_size)
+                .save(dir.join("ic_launcher_foreground.png"))?;
+            image::RgbImage::from_pixel(adaptive_size, adaptive_size, image::Rgb(matte.color))
+                .save(dir.join("ic_launcher_background.png"))?;
+
+            Ok(())
+        },
+    )?;
+
+    let anydpi_dir = android_dir.join("mipmap-anydpi-v26");
+    std::fs::create_dir_all(&anydpi_dir)
+        .with_context(|| format!("Failed to create directory '{anydpi_dir:?}'"))?;
+    std::fs::write(anydpi_dir.join("ic_launcher.xml"), ADAPTIVE_ICON_XML)?;
+    fit_square(&image, 512, 512).save(android_dir.join("playstore-icon.png"))?;
+
+    std::fs::create_dir_all(&ios_dir)
+        .with_context(|| format!("Failed to create directory '{ios_dir:?}'"))?;
+
+    let entries = IOS_APP_ICONS
+        .par_iter()
+        .map(|&(idiom, size, scale)| -> Result<String> {
+            let pixels = (size.parse::<f32>()? * scale as f32).round() as u32;
+            let filename = format!("AppIcon-{idiom}-{size}@{scale}x.png");
+
+            // App Store 不接受带透明通道的图标, 铺底色后去掉 alpha
+            let icon = fit_square(&image, pixels, pixels);
+            alpha::flatten(icon.into(), matte)
+                .save_with_format(ios_dir.join(&filename), ImageFormat::Png)?;
+
+            Ok(format!(
+                r#"    {{ "filename" : "{filename}", "idiom" : "{idiom}", "scale" : "{scale}x", "size" : "{size}x{size}" }}"#
+            ))
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    let contents = format!(
+        "{{\n  \"images\" : [\n{}\n  ],\n  \"info\" : {{ \"author\" : \"ImgZap\", \"version\" : 1 }}\n}}\n",
+        entries.join(",\n")
+    );
+    std::fs::write(ios_dir.join("Contents.json"), contents)
+        .with_context(|| "Failed to write Contents.json")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+    use crate::icc::ColorTarget;
+    use crate::testing::TempFile;
+
+    const DISPLAY_P3: &[u8] = include_bytes!("../tests/data/display-p3.icc");
+
+    #[test]
+    fn converted_profile_is_always_embedded() {
+        let profile = TempFile::with_data("icc", DISPLAY_P3);
+        let options = ConvertOptions {
+            color: ColorOptions {
+                target: ColorTarget::Profile,
+                profile_path: Some(profile.to_path_buf()),
+            },
+            metadata: MetadataPolicy::StripAll,
+            compress: CompressOptions {
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let red = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([255, 0, 0])));
+        let convert = |format: ImageFormatExt, output_path: &Path| {
+            convert_image(
+                red.clone(),
+                Orientation::NoTransforms,
+                Metadata::default(),
+                output_path,
+                &options,
+                &format,
+            )
+            .unwrap()
+        };
+
+        // 清除元数据时也要保留像素所在的配置文件, 压缩也不能去掉
+        let png = TempFile::new("png");
+        convert(ImageFormatExt::Png, &png);
+        let (image, _, metadata) = decode_image(&png).unwrap();
+        assert_eq!(metadata.icc.as_deref(), Some(DISPLAY_P3));
+        assert_ne!(image.to_rgb8().get_pixel(0, 0).0, [255, 0, 0]);
+
+        // 无法嵌入配置文件的格式改为输出 sRGB
+        let bmp = TempFile::new("bmp");
+        let converted = convert(ImageFormatExt::Bmp, &bmp);
+        assert_eq!(converted.notes.len(), 1);
+        let (image, _, _) = decode_image(&bmp).unwrap();
+        assert_eq!(image.to_rgb8().get_pixel(0, 0).0, [255, 0, 0]);
+    }
+}
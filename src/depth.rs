@@ -0,0 +1,169 @@
+use std::io::Write;
+
+use anyhow::Result;
+use image::{ColorType, DynamicImage, ImageBuffer, ImageFormat};
+use rav1e::prelude::PixelRange;
+use ravif::{BitDepth, Encoder, MatrixCoefficients};
+use rayon::prelude::*;
+
+use crate::i18n::{tr, trf};
+
+/// 每个通道的位数: 8, 16 或 32 (浮点)
+pub fn bit_depth(color: ColorType) -> u8 {
+    color.bytes_per_pixel() / color.channel_count() * 8
+}
+
+/// 4x4 Bayer 矩阵, 作为量化时的阈值
+const BAYER: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+/// 转换为目标格式能保存的最高精度, 精度降低时返回提示
+pub fn fit(
+    image: DynamicImage,
+    format: ImageFormat,
+    dither: bool,
+) -> (DynamicImage, Option<String>) {
+    let depth = bit_depth(image.color());
+    let has_alpha = image.color().has_alpha();
+    let name = format!("{format:?}").to_uppercase();
+
+    match format {
+        // 浮点格式, 8/16 位输入直接提升
+        ImageFormat::Hdr => {
+            let note = has_alpha.then(|| tr("HDR 不支持透明, 已丢弃 alpha").to_string());
+            (DynamicImage::ImageRgb32F(image.into_rgb32f()), note)
+        }
+        ImageFormat::OpenExr if has_alpha => {
+            (DynamicImage::ImageRgba32F(image.into_rgba32f()), None)
+        }
+        ImageFormat::OpenExr => (DynamicImage::ImageRgb32F(image.into_rgb32f()), None),
+        ImageFormat::Png | ImageFormat::Tiff | ImageFormat::Avif if depth > 8 => {
+            let note = if format == ImageFormat::Avif {
+                Some(trf(
+                    "AVIF 只按 8 位或 10 位写入, 精度从 {} 位降到 10 位",
+                    &[&depth],
+                ))
+            } else {
+                (depth == 32).then(|| {
+                    trf(
+                        "{} 不支持浮点, 已转为 16 位, 超出范围的高光被截断",
+                        &[&name],
+                    )
+                })
+            };
+            // TIFF 和 10 位 AVIF 编码器只接受 RGB(A) 或灰度, 不接受灰度 + alpha
+            let image = match image {
+                DynamicImage::ImageLuma16(_) if format != ImageFormat::Avif => image,
+                DynamicImage::ImageLumaA16(_) if format == ImageFormat::Png => image,
+                DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => image,
+                image if has_alpha => DynamicImage::ImageRgba16(image.into_rgba16()),
+                image => DynamicImage::ImageRgb16(image.into_rgb16()),
+            };
+            (image, note)
+        }
+        ImageFormat::Tiff if image.color() == ColorType::La8 => {
+            (DynamicImage::ImageRgba8(image.into_rgba8()), None)
+        }
+        _ if depth > 8 => {
+            let note = reduced_note(&name, depth, dither);
+            (to_8bit(&image, dither), Some(note))
+        }
+        _ => (image, None),
+    }
+}
+
+/// 降到 8 位时给出的提示
+pub fn reduced_note(name: &str, depth: u8, dither: bool) -> String {
+    let text = if dither {
+        "{} 只支持 8 位, 精度从 {} 位降到 8 位 (已抖动)"
+    } else {
+        "{} 只支持 8 位, 精度从 {} 位降到 8 位"
+    };
+    trf(text, &[&name, &depth])
+}
+
+/// 高位深转 8 位, 保留通道布局; 抖动只作用于颜色通道
+pub fn to_8bit(image: &DynamicImage, dither: bool) -> DynamicImage {
+    let (width, height) = (image.width(), image.height());
+    let channels = image.color().channel_count() as usize;
+    let samples = match channels {
+        1 => image.to_luma32f().into_raw(),
+        2 => image.to_luma_alpha32f().into_raw(),
+        3 => image.to_rgb32f().into_raw(),
+        _ => image.to_rgba32f().into_raw(),
+    };
+    let color_channels = if image.color().has_alpha() {
+        channels - 1
+    } else {
+        channels
+    };
+
+    let row_len = width as usize * channels;
+    let mut data = vec![0u8; samples.len()];
+    data.par_chunks_mut(row_len)
+        .zip(samples.par_chunks(row_len))
+        .enumerate()
+        .for_each(|(y, (output, input))| {
+            for (i, (output, input)) in output.iter_mut().zip(input).enumerate() {
+                let threshold = if dither && i % channels < color_channels {
+                    (BAYER[y % 4][(i / channels) % 4] + 0.5) / 16.0
+                } else {
+                    0.5
+                };
+                *output = (input.clamp(0.0, 1.0) * 255.0 + threshold)
+                    .floor()
+                    .min(255.0) as u8;
+            }
+        });
+
+    match channels {
+        1 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, data).unwrap()),
+        2 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(width, height, data).unwrap()),
+        3 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, data).unwrap()),
+        _ => DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, data).unwrap()),
+    }
+}
+
+/// 16 位图片编码为 10 位 AVIF, 速度与 image 的 AvifEncoder 默认值一致 (4)
+///
+/// ravif 只能编码 8 位和 10 位, 不支持 12 位
+pub fn write_avif_10bit(image: &DynamicImage, mut writer: impl Write, quality: u8) -> Result<()> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let rgba = image.to_rgba16();
+    let has_alpha = image.color().has_alpha();
+
+    // 与 ravif 相同的 BT.601 全范围 YCbCr
+    const KR: f32 = 0.299;
+    const KB: f32 = 0.114;
+    let scale = 1023.0 / 65535.0;
+    let planes = rgba.pixels().map(|pixel| {
+        let [r, g, b, _] = pixel.0.map(|v| v as f32 * scale);
+        let y = KR * r + (1.0 - KR - KB) * g + KB * b;
+        let cb = (b - y) * 0.5 / (1.0 - KB) + 512.0;
+        let cr = (r - y) * 0.5 / (1.0 - KR) + 512.0;
+        [y, cb, cr].map(|v| v.round().clamp(0.0, 1023.0) as u16)
+    });
+    let alpha = has_alpha.then(|| rgba.pixels().map(|pixel| pixel.0[3] >> 6));
+
+    let encoded = Encoder::new()
+        .with_quality(quality as f32)
+        .with_alpha_quality(quality as f32)
+        .with_speed(4)
+        .with_bit_depth(BitDepth::Ten)
+        .encode_raw_planes_10_bit(
+            width,
+            height,
+            planes,
+            alpha,
+            PixelRange::Full,
+            MatrixCoefficients::BT601,
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to encode AVIF: {e}"))?;
+    writer.write_all(&encoded.avif_file)?;
+
+    Ok(())
+}
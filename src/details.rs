@@ -0,0 +1,162 @@
+use std::{ffi::OsStr, path::Path};
+
+use anyhow::{Context, Result};
+use image::ImageDecoder;
+
+use crate::ImageFormatExt;
+use crate::convert;
+use crate::depth;
+use crate::svg::{self, SvgOptions};
+use crate::tiff;
+
+/// 详情面板显示的信息, 只读文件头和元数据, 不解码像素
+#[derive(Debug, Clone, Default)]
+pub struct ImageDetails {
+    pub file_size: u64,
+    /// tika-magic 检测出的 MIME
+    pub mime: Option<String>,
+    /// 扩展名与检测出的格式不一致
+    pub extension_mismatch: bool,
+    pub dimensions: Option<(u32, u32)>,
+    pub color: Option<String>,
+    pub bit_depth: Option<u8>,
+    /// ICO 中每个图标的 (宽, 高, 每像素位数)
+    pub ico_entries: Vec<(u32, u32, u16)>,
+    pub svg_view_box: Option<String>,
+    /// 动画 PNG/WEBP 的帧数
+    pub frames: Option<u32>,
+    /// TIFF 的页数
+    pub pages: Option<usize>,
+    pub exif: Vec<(&'static str, String)>,
+}
+
+pub fn read(input_path: &Path, image_format: &ImageFormatExt) -> Result<ImageDetails> {
+    let file_size = std::fs::metadata(input_path)
+        .with_context(|| format!("Failed to read metadata of '{input_path:?}'"))?
+        .len();
+    let extension = input_path
+        .extension()
+        .and_then(OsStr::to_str)
+        .unwrap_or_default()
+        .to_lowercase();
+    let mut details = ImageDetails {
+        file_size,
+        mime: tika_magic::from_filepath(input_path).map(str::to_string),
+        extension_mismatch: !extension_matches(image_format, &extension),
+        ..Default::default()
+    };
+
+    match image_format {
+        ImageFormatExt::Svg => {
+            let (rtree, _) = svg::load_svg(input_path, &SvgOptions::default())?;
+            let size = rtree.size();
+            details.dimensions = Some((size.width().round() as u32, size.height().round() as u32));
+            details.svg_view_box = read_view_box(&std::fs::read_to_string(input_path)?);
+        }
+        ImageFormatExt::Ico => {
+            let file = std::fs::File::open(input_path)?;
+            let icon_dir = ico::IconDir::read(file)?;
+            details.ico_entries = icon_dir
+                .entries()
+                .iter()
+                .map(|entry| (entry.width(), entry.height(), entry.bits_per_pixel()))
+                .collect();
+            details.dimensions = details
+                .ico_entries
+                .iter()
+                .map(|&(width, height, _)| (width, height))
+                .max_by_key(|&(width, height)| width * height);
+        }
+        _ => {
+            let (decoder, _, metadata) = convert::open_image(input_path)?;
+            let color = decoder.color_type();
+            details.dimensions = Some(decoder.dimensions());
+            details.color = Some(format!("{color:?}"));
+            details.bit_depth = Some(depth::bit_depth(color));
+            details.exif = metadata.exif_fields();
+            match image_format {
+                ImageFormatExt::Tiff => details.pages = Some(tiff::page_count(input_path)?),
+                ImageFormatExt::Png | ImageFormatExt::WebP => {
+                    details.frames = frame_count(&std::fs::read(input_path)?)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(details)
+}
+
+fn extension_matches(image_format: &ImageFormatExt, extension: &str) -> bool {
+    match image_format {
+        ImageFormatExt::Svg => matches!(extension, "svg" | "svgz"),
+        ImageFormatExt::Ico => extension == "ico",
+        _ => image_format
+            .get_format()
+            .is_some_and(|format| format.extensions_str().contains(&extension)),
+    }
+}
+
+/// 根元素上的 viewBox 属性
+fn read_view_box(svg: &str) -> Option<String> {
+    let start = svg.find("<svg")?;
+    let tag = &svg[start..start + svg[start..].find('>')?];
+    let value = &tag[tag.find("viewBox")? + "viewBox".len()..];
+    let value = value.trim_start().strip_prefix('=')?.trim_start();
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    Some(value[..value.find(quote)?].trim().to_string())
+}
+
+/// APNG 的 acTL 块记录帧数, 动画 WEBP 每帧一个 ANMF 块; 静态图返回 None
+fn frame_count(data: &[u8]) -> Option<u32> {
+    let read_u32 = |offset: usize, big_endian: bool| -> Option<u32> {
+        let bytes = data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let mut offset = 8;
+        while let Some(length) = read_u32(offset, true) {
+            match data.get(offset + 4..offset + 8)? {
+                b"acTL" => return read_u32(offset + 8, true),
+                b"IDAT" | b"IEND" => return None,
+                _ => offset += length as usize + 12,
+            }
+        }
+        None
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        let mut offset = 12;
+        let mut frames = 0;
+        while let Some(length) = read_u32(offset + 4, false) {
+            if data.get(offset..offset + 4) == Some(b"ANMF") {
+                frames += 1;
+            }
+            // 块按偶数字节对齐
+            offset += 8 + length as usize + (length as usize & 1);
+        }
+        (frames > 0).then_some(frames)
+    } else {
+        None
+    }
+}
+
+/// 1536 -> "1.5 KB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+use iced::Font;
+
+use crate::i18n::tr;
+use crate::svg;
+
+/// 按顺序查找已安装的中文界面字体, 都没有时使用 iced 自带的字体
+const CANDIDATES: &[&str] = if cfg!(target_os = "windows") {
+    &["Microsoft YaHei UI", "Microsoft YaHei", "SimHei"]
+} else if cfg!(target_os = "macos") {
+    &["PingFang SC", "Hiragino Sans GB", "STHeiti"]
+} else {
+    &[
+        "Noto Sans CJK SC",
+        "Noto Sans SC",
+        "Source Han Sans SC",
+        "Source Han Sans CN",
+        "WenQuanYi Micro Hei",
+        "WenQuanYi Zen Hei",
+        "Droid Sans Fallback",
+        "AR PL UMing CN",
+    ]
+};
+
+/// 设置中的界面字体
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UiFont {
+    Auto,
+    Family(String),
+}
+
+impl fmt::Display for UiFont {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UiFont::Auto => f.write_str(tr("自动")),
+            UiFont::Family(family) => f.write_str(family),
+        }
+    }
+}
+
+/// 已安装的字体名称, 按名称排序
+pub fn families() -> Vec<String> {
+    let mut families = svg::system_fonts()
+        .faces()
+        .filter_map(|face| face.families.first().map(|(family, _)| family.clone()))
+        .collect::<Vec<_>>();
+    families.sort();
+    families.dedup();
+
+    families
+}
+
+/// 第一个已安装的候选字体, 都没有时界面中的中文可能无法显示
+pub fn candidate(families: &[String]) -> Option<&'static str> {
+    CANDIDATES
+        .iter()
+        .copied()
+        .find(|name| families.iter().any(|family| family == name))
+}
+
+/// 选择的字体已安装时使用它, 否则按平台查找候选字体
+pub fn ui_font(selected: Option<&str>, families: &[String]) -> Font {
+    match selected.filter(|name| families.iter().any(|family| family == name)) {
+        // iced 的字体名需要 'static, 只在启动时创建一次
+        Some(family) => Font::with_name(Box::<str>::leak(family.into())),
+        None => candidate(families).map_or(Font::DEFAULT, Font::with_name),
+    }
+}
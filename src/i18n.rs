@@ -0,0 +1,313 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    Chinese,
+    English,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::Chinese, Language::English];
+
+    /// 系统区域设置为中文时使用中文, 其他使用英文
+    pub fn system() -> Language {
+        match sys_locale::get_locale() {
+            Some(locale) if locale.to_lowercase().starts_with("zh") => Language::Chinese,
+            _ => Language::English,
+        }
+    }
+}
+
+/// 各语言用自己的文字显示, 不随界面语言变化
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Language::Chinese => "简体中文",
+            Language::English => "English",
+        })
+    }
+}
+
+// 界面语言是全局的, 选项的 Display 和转换提示在后台线程中也要用到
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::Chinese as u8);
+
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    if LANGUAGE.load(Ordering::Relaxed) == Language::English as u8 {
+        Language::English
+    } else {
+        Language::Chinese
+    }
+}
+
+/// 界面文字以中文原文为键, 缺少翻译时显示原文
+pub fn tr(text: &'static str) -> &'static str {
+    translate(language(), text)
+}
+
+pub fn translate(language: Language, text: &'static str) -> &'static str {
+    match language {
+        Language::Chinese => text,
+        Language::English => english(text).unwrap_or(text),
+    }
+}
+
+/// 翻译后按顺序填入 `{}` 占位符
+pub fn trf(text: &'static str, args: &[&dyn fmt::Display]) -> String {
+    let mut parts = tr(text).split("{}");
+    let mut result = parts.next().unwrap_or_default().to_string();
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+
+    result
+}
+
+fn english(text: &str) -> Option<&'static str> {
+    let text = match text {
+        "选择文件" => "Select files",
+        "选择文件夹" => "Select folders",
+        "清空" => "Clear",
+        "转换" => "Convert",
+        "转换中..." => "Converting...",
+        "转换 (含 {} 个被筛选隐藏的文件)" => "Convert (incl. {} hidden by filters)",
+        "合并为 TIFF" => "Merge into TIFF",
+        "全部" => "All",
+        "搜索文件名或路径" => "Search name or path",
+        "最近的文件夹" => "Recent folders",
+        "降序" => "Descending",
+        "升序" => "Ascending",
+        "上次退出时列表中有 {} 个文件" => "{} files were in the list last time",
+        "恢复" => "Restore",
+        "忽略" => "Dismiss",
+        "< 选 择 所 有 >" => "< Select all >",
+        "按格式选择" => "Select by format",
+        "反选" => "Invert",
+        "移除选中" => "Remove checked",
+        "<未知文件名>" => "<unknown file name>",
+        "<未知文件夹>" => "<unknown folder>",
+        "打开输出文件夹" => "Open output folder",
+        "复制错误" => "Copy error",
+        "{} -> {}: 元数据 {}" => "{} -> {}: metadata {}",
+        "收起日志" => "Hide log",
+        "展开日志" => "Show log",
+        "{} 个成功, {} 个失败" => "{} succeeded, {} failed",
+        "按名称" => "By name",
+        "按路径" => "By path",
+        "按大小" => "By size",
+        "按格式" => "By format",
+        "按尺寸" => "By dimensions",
+        "按修改时间" => "By date modified",
+        "等待中" => "Queued",
+        "转换中" => "Running",
+        "完成" => "Done",
+        "失败" => "Failed",
+        "选择输出文件夹" => "Select output folder",
+        "选择字体文件夹" => "Select font folder",
+        "选择 ICC 配置文件" => "Select ICC profile",
+        "保存多页 TIFF" => "Save multi-page TIFF",
+        "语言" => "Language",
+        "第 {} 页无法解码, 已跳过: {}" => {
+            "Page {} could not be decoded and was skipped: {}"
+        }
+        "输出文件名与其他文件重复, 已改为 {}" => {
+            "Output name is used by another file, renamed to {}"
+        }
+        "界面字体" => "UI font",
+        "自动" => "Auto",
+        "重启后生效" => "Takes effect after restart",
+        "没有找到中文字体, 中文可能显示为方框, 请选择其他字体" => {
+            "No Chinese font found, Chinese text may show as boxes. Choose another font"
+        }
+        "预设" => "Presets",
+        "选择预设" => "Select preset",
+        "预设名称" => "Preset name",
+        "保存" => "Save",
+        "删除" => "Delete",
+        "输出" => "Output",
+        "与原文件相同" => "Same as source",
+        "文件名" => "File name",
+        "SVG 渲染" => "SVG rendering",
+        "多尺寸: 24, 48 / 1x, 2x" => "Sizes: 24, 48 / 1x, 2x",
+        "留白" => "Padding",
+        "背景" => "Background",
+        "默认字体" => "Default font",
+        "添加字体文件夹" => "Add font folder",
+        "阻止外部资源" => "Block external resources",
+        "SVG 精简" => "SVG optimization",
+        "精简 SVG 输出" => "Optimize SVG output",
+        "坐标精度" => "Coordinate precision",
+        "合并路径" => "Merge paths",
+        "压缩空白" => "Minify whitespace",
+        "矢量化 (SVG)" => "Vectorize (SVG)",
+        "彩色" => "Color",
+        "堆叠" => "Stacked",
+        "过滤斑点" => "Filter speckle",
+        "颜色精度" => "Color precision",
+        "层差" => "Layer difference",
+        "角度阈值" => "Corner threshold",
+        "长度阈值" => "Length threshold",
+        "拼接阈值" => "Splice threshold",
+        "缩放" => "Resize",
+        "宽" => "Width",
+        "高" => "Height",
+        "不放大" => "Don't upscale",
+        "EXIF 方向" => "EXIF orientation",
+        "元数据" => "Metadata",
+        "压缩 PNG" => "Compress PNG",
+        "保留文件时间" => "Keep file times",
+        "降低位深时抖动" => "Dither when reducing bit depth",
+        "高位深图片保存为 AVIF 时按 10 位写入" => {
+            "High bit depth images are saved as 10-bit AVIF"
+        }
+        "透明底色 (JPEG/BMP/应用图标)" => "Matte color (JPEG/BMP/app icon)",
+        "线性混合边缘" => "Blend edges linearly",
+        "TIFF 压缩" => "TIFF compression",
+        "颜色管理" => "Color management",
+        "未选择配置文件" => "No profile selected",
+        "选择 ICC 文件" => "Select ICC file",
+        "质量 (JPEG/AVIF)" => "Quality (JPEG/AVIF)",
+        "压缩等级" => "Compression level",
+        "{}: 左侧原图, 右侧 {}" => "{}: original on the left, {} on the right",
+        "返回列表" => "Back to list",
+        "大小: {} -> {} ({}%)" => "Size: {} -> {} ({}%)",
+        "无损" => "Lossless",
+        "关闭" => "Close",
+        "读取中..." => "Loading...",
+        "格式" => "Format",
+        "文件大小" => "File size",
+        "未知" => "Unknown",
+        "尺寸" => "Dimensions",
+        "颜色类型" => "Color type",
+        "位深" => "Bit depth",
+        "{} 位" => "{}-bit",
+        "动画帧数" => "Frames",
+        "页数" => "Pages",
+        "扩展名与文件内容不一致" => "Extension doesn't match file content",
+        "ICO 图标" => "ICO icons",
+        "{} x {}, {} 位" => "{} x {}, {}-bit",
+        "对比预览" => "Compare preview",
+        "生成中..." => "Generating...",
+        "生成预览" => "Generate preview",
+        "不转换" => "Keep",
+        "转换为 sRGB" => "Convert to sRGB",
+        "转换为指定配置文件" => "Convert to profile",
+        "按 EXIF 旋转" => "Rotate by EXIF",
+        "保留方向标记" => "Keep orientation tag",
+        "全部保留" => "Keep all",
+        "仅 ICC 和版权" => "ICC and copyright only",
+        "全部清除" => "Strip all",
+        "不缩放" => "No resize",
+        "最大宽高" => "Max size",
+        "指定宽高" => "Exact size",
+        "百分比" => "Percent",
+        "长边" => "Long edge",
+        "短边" => "Short edge",
+        "拉伸" => "Stretch",
+        "适应" => "Fit",
+        "填充裁剪" => "Fill and crop",
+        "填充" => "Fill",
+        "左上" => "Top left",
+        "上" => "Top",
+        "右上" => "Top right",
+        "左" => "Left",
+        "居中" => "Center",
+        "右" => "Right",
+        "左下" => "Bottom left",
+        "下" => "Bottom",
+        "右下" => "Bottom right",
+        "原始尺寸" => "Original size",
+        "宽度" => "Width",
+        "高度" => "Height",
+        "宽 x 高" => "Width x height",
+        "缩放倍数" => "Scale",
+        "不压缩" => "None",
+        "默认" => "Default",
+        "标志" => "Logo",
+        "海报" => "Poster",
+        "照片" => "Photo",
+        "黑白线稿" => "Line art",
+        "自定义" => "Custom",
+        "曲线" => "Spline",
+        "多边形" => "Polygon",
+        "像素" => "Pixel",
+        "网页导出" => "Web export",
+        "网页导出 (WEBP 无损 + AVIF 质量 60)" => "Web export (lossless WEBP + AVIF q60)",
+        "应用图标" => "App icon",
+        "应用图标 (ICO + APP ICON, 不含 ICNS)" => "App icon (ICO + APP ICON, no ICNS)",
+        "PNG 无损压缩" => "Lossless PNG shrink",
+        "相机厂商" => "Camera make",
+        "相机型号" => "Camera model",
+        "方向" => "Orientation",
+        "拍摄时间" => "Date taken",
+        "修改时间" => "Date modified",
+        "曝光时间" => "Exposure time",
+        "光圈" => "Aperture",
+        "焦距" => "Focal length",
+        "软件" => "Software",
+        "作者" => "Artist",
+        "版权" => "Copyright",
+        "无" => "None",
+        "输出尺寸与原图不同, 无法计算画质指标" => {
+            "Output size differs from the source, quality metrics are unavailable"
+        }
+        "无法解码 {} 预览: {}" => "Cannot decode the {} preview: {}",
+        "WEBP 总是无损编码, 质量设置对它不起作用" => {
+            "WEBP is always encoded losslessly, the quality setting doesn't apply to it"
+        }
+        "已阻止外部资源: {}" => "Blocked external resource: {}",
+        "找不到引用的资源: {}" => "Missing linked resource: {}",
+        "共 {} 页, 已拆分为带序号的文件" => "{} pages, split into numbered files",
+        "已合并 {} 个文件, 共 {} 页" => "Merged {} files, {} pages in total",
+        "{} 无法嵌入 ICC 配置文件, 已转换为 sRGB" => {
+            "{} cannot embed an ICC profile, converted to sRGB instead"
+        }
+        "无法转换颜色, 已保留原 ICC 配置文件: {}" => {
+            "Cannot convert colors, kept the original ICC profile: {}"
+        }
+        "{} 无法保存方向标记, 已按 EXIF 方向旋转" => {
+            "{} cannot store the orientation tag, rotated by the EXIF orientation"
+        }
+        "{} 无法保存元数据, 已丢弃 {}" => "{} cannot store metadata, dropped {}",
+        "{} 超过 JPEG 元数据段的 64 KB 上限, 已丢弃" => {
+            "{} exceeds the 64 KB limit of a JPEG metadata segment and was dropped"
+        }
+        "{} 只支持 8 位, 精度从 {} 位降到 8 位" => {
+            "{} only supports 8-bit, precision reduced from {} to 8 bits"
+        }
+        "{} 只支持 8 位, 精度从 {} 位降到 8 位 (已抖动)" => {
+            "{} only supports 8-bit, precision reduced from {} to 8 bits (dithered)"
+        }
+        "HDR 不支持透明, 已丢弃 alpha" => {
+            "HDR doesn't support transparency, alpha was dropped"
+        }
+        "AVIF 只按 8 位或 10 位写入, 精度从 {} 位降到 10 位" => {
+            "AVIF is written as 8-bit or 10-bit only, precision reduced from {} to 10 bits"
+        }
+        "{} 不支持浮点, 已转为 16 位, 超出范围的高光被截断" => {
+            "{} doesn't support floating point, converted to 16-bit and out-of-range highlights were clipped"
+        }
+        "用法: ImgZap --preset <预设名称> <文件或文件夹>...\n       ImgZap --list-presets" => {
+            "Usage: ImgZap --preset <preset name> <files or folders>...\n       ImgZap --list-presets"
+        }
+        "未知的预设 '{}', 可用的预设: {}" => "Unknown preset '{}', available presets: {}",
+        "未知的参数 '{}'" => "Unknown option '{}'",
+        "找不到 '{}'" => "'{}' not found",
+        "没有找到可转换的图片" => "No convertible images found",
+        "{} -> {}: 失败: {}" => "{} -> {}: failed: {}",
+        _ => return None,
+    };
+
+    Some(text)
+}
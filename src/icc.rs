@@ -0,0 +1,503 @@
+use std::{fmt, path::PathBuf};
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorTarget {
+    /// 不改动像素, ICC 按元数据策略处理
+    #[default]
+    Keep,
+    Srgb,
+    /// 转换到用户选择的 .icc/.icm 文件
+    Profile,
+}
+
+impl ColorTarget {
+    pub const ALL: [ColorTarget; 3] = [ColorTarget::Keep, ColorTarget::Srgb, ColorTarget::Profile];
+}
+
+impl fmt::Display for ColorTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            ColorTarget::Keep => "不转换",
+            ColorTarget::Srgb => "转换为 sRGB",
+            ColorTarget::Profile => "转换为指定配置文件",
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorOptions {
+    pub target: ColorTarget,
+    pub profile_path: Option<PathBuf>,
+}
+
+impl ColorOptions {
+    /// 目标配置文件和输出应嵌入的 ICC, 不转换时返回 None
+    ///
+    /// sRGB 不嵌入配置文件, 未标记的图片默认按 sRGB 显示
+    pub fn target_profile(&self) -> Result<Option<(Profile, Option<Vec<u8>>)>> {
+        match self.target {
+            ColorTarget::Keep => Ok(None),
+            ColorTarget::Srgb => Ok(Some((Profile::srgb(), None))),
+            ColorTarget::Profile => {
+                let path = self
+                    .profile_path
+                    .as_ref()
+                    .context("No output ICC profile selected")?;
+                let data = std::fs::read(path)
+                    .with_context(|| format!("Failed to read ICC profile '{path:?}'"))?;
+                let profile = Profile::parse(&data)
+                    .with_context(|| format!("Unsupported ICC profile '{path:?}'"))?;
+                anyhow::ensure!(
+                    !profile.gray,
+                    "Output ICC profile '{path:?}' is a gray profile, only RGB profiles are supported"
+                );
+                Ok(Some((profile, Some(data))))
+            }
+        }
+    }
+}
+
+/// 色调曲线, 统一为 ICC `para` 第 4 类:
+/// x >= d 时 (a * x + b) ^ g + e, 否则 c * x + f
+#[derive(Debug, Clone)]
+enum Curve {
+    Parametric([f32; 7]),
+    Table(Vec<f32>),
+}
+
+impl Curve {
+    fn gamma(g: f32) -> Self {
+        Curve::Parametric([g, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0])
+    }
+
+    fn eval(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            Curve::Parametric([g, a, b, c, d, e, f]) => {
+                if x >= *d {
+                    (a * x + b).max(0.0).powf(*g) + e
+                } else {
+                    c * x + f
+                }
+            }
+            Curve::Table(table) => {
+                let position = x * (table.len() - 1) as f32;
+                let index = (position as usize).min(table.len() - 2);
+                let t = position - index as f32;
+                table[index] * (1.0 - t) + table[index + 1] * t
+            }
+        }
+    }
+
+    /// 均匀采样的反函数表, 曲线按单调递增处理
+    fn inverse_table(&self) -> Vec<f32> {
+        (0..INVERSE_SIZE)
+            .map(|i| {
+                let y = i as f32 / (INVERSE_SIZE - 1) as f32;
+                let (mut low, mut high) = (0.0f32, 1.0f32);
+                for _ in 0..24 {
+                    let mid = (low + high) / 2.0;
+                    if self.eval(mid) < y {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                (low + high) / 2.0
+            })
+            .collect()
+    }
+}
+
+const INVERSE_SIZE: usize = 4096;
+
+/// PCS 的白点 D50
+const D50: [f32; 3] = [0.9642, 1.0, 0.8249];
+
+/// 矩阵/TRC 型 RGB 配置文件 (Display P3, Adobe RGB, sRGB 等) 或灰度配置文件
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// RGB -> PCS XYZ (D50), 按行存放
+    matrix: [[f32; 3]; 3],
+    curves: [Curve; 3],
+    /// 灰度配置文件按 R = G = B 处理, 三条曲线相同, 矩阵把灰度映射到 D50 白点
+    gray: bool,
+}
+
+impl Profile {
+    pub fn srgb() -> Self {
+        let curve = Curve::Parametric([
+            2.4,
+            1.0 / 1.055,
+            0.055 / 1.055,
+            1.0 / 12.92,
+            0.04045,
+            0.0,
+            0.0,
+        ]);
+        Profile {
+            // 已适配到 D50 的 sRGB 原色, 与常见 sRGB 配置文件一致
+            matrix: [
+                [0.4361, 0.3851, 0.1431],
+                [0.2225, 0.7169, 0.0606],
+                [0.0139, 0.0971, 0.7141],
+            ],
+            curves: [curve.clone(), curve.clone(), curve],
+            gray: false,
+        }
+    }
+
+    /// 原色和曲线几乎相同, 转换不会改变颜色
+    pub fn same_as(&self, other: &Profile) -> bool {
+        let matrix_close = self
+            .matrix
+            .iter()
+            .flatten()
+            .zip(other.matrix.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < 0.002);
+        let curves_close = self.curves.iter().zip(&other.curves).all(|(a, b)| {
+            (0..=32).all(|i| {
+                let x = i as f32 / 32.0;
+                (a.eval(x) - b.eval(x)).abs() < 0.001
+            })
+        });
+
+        self.gray == other.gray && matrix_close && curves_close
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        anyhow::ensure!(data.len() >= 132, "ICC profile is truncated");
+        let gray = match &data[16..20] {
+            b"RGB " => false,
+            b"GRAY" => true,
+            _ => anyhow::bail!("Only RGB and gray profiles are supported"),
+        };
+        anyhow::ensure!(
+            &data[20..24] == b"XYZ ",
+            "Only XYZ connection space is supported"
+        );
+
+        let tag_count = read_u32(data, 128)? as usize;
+        let tag = |signature: &[u8; 4]| -> Result<&[u8]> {
+            (0..tag_count)
+                .map(|i| 132 + i * 12)
+                .find(|&entry| data.get(entry..entry + 4) == Some(signature))
+                .and_then(|entry| {
+                    let offset = read_u32(data, entry + 4).ok()? as usize;
+                    let size = read_u32(data, entry + 8).ok()? as usize;
+                    data.get(offset..offset + size)
+                })
+                .with_context(|| {
+                    format!(
+                        "Missing tag '{}', only matrix/TRC profiles are supported",
+                        String::from_utf8_lossy(signature)
+                    )
+                })
+        };
+
+        let xyz = |signature: &[u8; 4]| -> Result<[f32; 3]> {
+            let xyz = tag(signature)?;
+            anyhow::ensure!(xyz.len() >= 20 && &xyz[..4] == b"XYZ ", "Invalid XYZ tag");
+            Ok([
+                read_s15_fixed16(xyz, 8)?,
+                read_s15_fixed16(xyz, 12)?,
+                read_s15_fixed16(xyz, 16)?,
+            ])
+        };
+        if gray {
+            let curve = parse_curve(tag(b"kTRC")?)?;
+            return Ok(Profile {
+                matrix: D50.map(|white| [white / 3.0; 3]),
+                curves: [curve.clone(), curve.clone(), curve],
+                gray,
+            });
+        }
+        let (r, g, b) = (xyz(b"rXYZ")?, xyz(b"gXYZ")?, xyz(b"bXYZ")?);
+
+        Ok(Profile {
+            // 原色的 XYZ 是矩阵的列
+            matrix: [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]],
+            curves: [
+                parse_curve(tag(b"rTRC")?)?,
+                parse_curve(tag(b"gTRC")?)?,
+                parse_curve(tag(b"bTRC")?)?,
+            ],
+            gray,
+        })
+    }
+}
+
+fn parse_curve(data: &[u8]) -> Result<Curve> {
+    match data.get(..4) {
+        Some(b"curv") => {
+            let count = read_u32(data, 8)? as usize;
+            match count {
+                0 => Ok(Curve::gamma(1.0)),
+                1 => Ok(Curve::gamma(read_u16(data, 12)? as f32 / 256.0)),
+                _ => (0..count)
+                    .map(|i| Ok(read_u16(data, 12 + i * 2)? as f32 / 65535.0))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Curve::Table),
+            }
+        }
+        Some(b"para") => {
+            let kind = read_u16(data, 8)?;
+            let count = [1, 3, 4, 5, 7]
+                .get(kind as usize)
+                .context("Unknown parametric curve")?;
+            let p = (0..*count)
+                .map(|i| read_s15_fixed16(data, 12 + i * 4))
+                .collect::<Result<Vec<_>>>()?;
+            // 各类参数曲线换算成第 4 类
+            let params = match kind {
+                0 => [p[0], 1.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                1 => [p[0], p[1], p[2], 0.0, -p[2] / p[1], 0.0, 0.0],
+                2 => [p[0], p[1], p[2], 0.0, -p[2] / p[1], p[3], p[3]],
+                3 => [p[0], p[1], p[2], p[3], p[4], 0.0, 0.0],
+                _ => [p[0], p[1], p[2], p[3], p[4], p[5], p[6]],
+            };
+            Ok(Curve::Parametric(params))
+        }
+        _ => anyhow::bail!("Unsupported tone curve type"),
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .context("ICC profile is truncated")?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .context("ICC profile is truncated")?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_s15_fixed16(data: &[u8], offset: usize) -> Result<f32> {
+    Ok(read_u32(data, offset)? as i32 as f32 / 65536.0)
+}
+
+fn invert(m: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+        (m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]) / det
+    };
+    [
+        [
+            cofactor(1, 2, 1, 2),
+            -cofactor(0, 2, 1, 2),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            -cofactor(1, 2, 0, 2),
+            cofactor(0, 2, 0, 2),
+            -cofactor(0, 1, 0, 2),
+        ],
+        [
+            cofactor(1, 2, 0, 1),
+            -cofactor(0, 2, 0, 1),
+            cofactor(0, 1, 0, 1),
+        ],
+    ]
+}
+
+fn multiply(a: [[f32; 3]; 3], b: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+struct Transform {
+    matrix: [[f32; 3]; 3],
+    source: [Curve; 3],
+    inverse: [Vec<f32>; 3],
+}
+
+impl Transform {
+    fn new(source: &Profile, target: &Profile) -> Self {
+        Transform {
+            matrix: multiply(invert(target.matrix), source.matrix),
+            source: source.curves.clone(),
+            inverse: [0, 1, 2].map(|i| target.curves[i].inverse_table()),
+        }
+    }
+
+    fn encode(&self, channel: usize, linear: f32) -> f32 {
+        let table = &self.inverse[channel];
+        let position = linear.clamp(0.0, 1.0) * (table.len() - 1) as f32;
+        let index = (position as usize).min(table.len() - 2);
+        let t = position - index as f32;
+        table[index] * (1.0 - t) + table[index + 1] * t
+    }
+
+    fn apply(&self, linear: [f32; 3]) -> [f32; 3] {
+        [0, 1, 2].map(|i| {
+            let value = (0..3).map(|k| self.matrix[i][k] * linear[k]).sum();
+            self.encode(i, value)
+        })
+    }
+}
+
+/// 转换后的灰度图 R = G = B, 还原为灰度, 保持位深
+pub fn to_luma(image: DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgb8(_) => DynamicImage::ImageLuma8(image.into_luma8()),
+        DynamicImage::ImageRgba8(_) => DynamicImage::ImageLumaA8(image.into_luma_alpha8()),
+        DynamicImage::ImageRgb16(_) => DynamicImage::ImageLuma16(image.into_luma16()),
+        DynamicImage::ImageRgba16(_) => DynamicImage::ImageLumaA16(image.into_luma_alpha16()),
+        image => image,
+    }
+}
+
+/// 把像素从 `source` 转到 `target`, 8 位图查表处理, 其余按浮点处理后还原位深
+pub fn transform(image: DynamicImage, source: &Profile, target: &Profile) -> DynamicImage {
+    let transform = Transform::new(source, target);
+    let has_alpha = image.color().has_alpha();
+
+    if image.color().bytes_per_pixel() / image.color().channel_count() == 1 {
+        let linearize: [Vec<f32>; 3] = [0, 1, 2].map(|i| {
+            (0..=255)
+                .map(|value| transform.source[i].eval(value as f32 / 255.0))
+                .collect()
+        });
+        let mut rgba = image.into_rgba8();
+        rgba.par_chunks_mut(4).for_each(|pixel| {
+            let linear = [0, 1, 2].map(|i| linearize[i][pixel[i] as usize]);
+            let rgb = transform.apply(linear);
+            for (i, value) in rgb.into_iter().enumerate() {
+                pixel[i] = (value * 255.0).round() as u8;
+            }
+        });
+        return if has_alpha {
+            DynamicImage::ImageRgba8(rgba)
+        } else {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(rgba).into_rgb8())
+        };
+    }
+
+    let is_float = matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    );
+    let mut rgba = image.into_rgba32f();
+    rgba.par_chunks_mut(4).for_each(|pixel| {
+        let linear = [0, 1, 2].map(|i| transform.source[i].eval(pixel[i]));
+        pixel[..3].copy_from_slice(&transform.apply(linear));
+    });
+
+    let image = DynamicImage::ImageRgba32F(rgba);
+    match (has_alpha, is_float) {
+        (true, true) => image,
+        (false, true) => DynamicImage::ImageRgb32F(image.into_rgb32f()),
+        (true, false) => DynamicImage::ImageRgba16(image.into_rgba16()),
+        (false, false) => DynamicImage::ImageRgb16(image.into_rgb16()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Rgb, RgbImage};
+
+    use super::*;
+    use crate::testing::TempFile;
+
+    // GIMP 内置的 sRGB
+    const SRGB: &[u8] = include_bytes!("../tests/data/srgb-gimp.icc");
+    // 按 Apple Display P3 的原色和 sRGB 曲线生成
+    const DISPLAY_P3: &[u8] = include_bytes!("../tests/data/display-p3.icc");
+    const GRAY: &[u8] = include_bytes!("../tests/data/gray-gamma22.icc");
+
+    fn gradient() -> RgbImage {
+        RgbImage::from_fn(64, 64, |x, y| {
+            Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8])
+        })
+    }
+
+    fn max_difference(a: &RgbImage, b: &RgbImage) -> u8 {
+        a.pixels()
+            .zip(b.pixels())
+            .flat_map(|(a, b)| (0..3).map(move |i| a[i].abs_diff(b[i])))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_srgb() {
+        let profile = Profile::parse(SRGB).unwrap();
+        assert!(!profile.gray);
+        assert!(profile.same_as(&Profile::srgb()));
+    }
+
+    #[test]
+    fn parse_display_p3() {
+        let profile = Profile::parse(DISPLAY_P3).unwrap();
+        assert!(!profile.same_as(&Profile::srgb()));
+        assert!((profile.matrix[0][0] - 0.5151).abs() < 0.001);
+        assert!((profile.matrix[1][1] - 0.6922).abs() < 0.001);
+
+        // sRGB 的纯红在 Display P3 中约为 (234, 51, 35)
+        let red = RgbImage::from_pixel(1, 1, Rgb([255, 0, 0]));
+        let p3 = transform(red.into(), &Profile::srgb(), &profile).into_rgb8();
+        let [r, g, b] = p3.get_pixel(0, 0).0;
+        assert!(r.abs_diff(234) <= 1 && g.abs_diff(51) <= 1 && b.abs_diff(35) <= 1);
+    }
+
+    #[test]
+    fn parse_gray() {
+        let profile = Profile::parse(GRAY).unwrap();
+        assert!(profile.gray);
+        assert!((profile.curves[0].eval(0.5) - 0.5f32.powf(2.2)).abs() < 0.001);
+
+        // 灰度配置文件不能作为输出
+        let file = TempFile::with_data("icc", GRAY);
+        let options = ColorOptions {
+            target: ColorTarget::Profile,
+            profile_path: Some(file.to_path_buf()),
+        };
+        assert!(options.target_profile().is_err());
+    }
+
+    #[test]
+    fn gray_stays_gray() {
+        let image = GrayImage::from_fn(16, 16, |x, y| image::Luma([(x * 16 + y) as u8]));
+        let source = Profile::parse(GRAY).unwrap();
+        let converted = to_luma(transform(image.into(), &source, &Profile::srgb()));
+        assert!(matches!(converted, DynamicImage::ImageLuma8(_)));
+    }
+
+    #[test]
+    fn identity_round_trip() {
+        let image = gradient();
+        let srgb = Profile::srgb();
+        let p3 = Profile::parse(DISPLAY_P3).unwrap();
+
+        let same = transform(image.clone().into(), &p3, &p3).into_rgb8();
+        assert!(max_difference(&image, &same) <= 1);
+
+        // 中间结果用 16 位, 8 位量化本身就会让饱和色偏差几个色阶
+        let there = transform(
+            DynamicImage::ImageRgb8(image.clone()).into_rgb16().into(),
+            &srgb,
+            &p3,
+        );
+        let back = transform(there, &p3, &srgb).into_rgb8();
+        assert!(max_difference(&image, &back) <= 1);
+    }
+}
@@ -1,35 +1,103 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod alpha;
+mod cli;
+mod compare;
 mod compress;
+mod config;
 mod convert;
+mod depth;
+mod details;
+mod font;
+mod i18n;
+mod icc;
+mod metadata;
+mod preset;
+mod resize;
+mod svg;
+#[cfg(test)]
+mod testing;
+mod tiff;
+mod trace;
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ffi::OsStr,
+    fmt,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 
 use iced::{
-    Element, Font, Size, Subscription, Task, Theme,
-    widget::{Column, button, checkbox, column, container, row, scrollable},
+    Element, Size, Subscription, Task, Theme,
+    keyboard::{self, Modifiers},
+    widget::image::Handle,
+    widget::{
+        Column, button, checkbox, column, container, pick_list, row, scrollable, slider, text,
+        text_input,
+    },
     window::{Settings, icon},
 };
 use rfd::{AsyncFileDialog, FileHandle};
+use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
 
+use alpha::MatteOptions;
+use compare::Preview;
+use compress::CompressOptions;
+use config::Config;
+use convert::{ConvertEvent, ConvertOptions, ConvertReport};
+use details::ImageDetails;
+use font::UiFont;
+use i18n::{Language, tr, trf};
+use icc::ColorTarget;
+use metadata::{MetadataPolicy, OrientationMode};
+use preset::Preset;
+use resize::{CropAnchor, ResizeFilter, ResizeFit, ResizeKind, ResizeOptions};
+use svg::{SvgFit, SvgOptimize, SvgSize, SvgSizeKind};
+use tiff::TiffCompression;
+use trace::{TraceCurve, TraceOptions, TracePreset};
+
 fn main() -> iced::Result {
-    iced::application(App::default, App::update, App::view)
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if cli::is_cli(&args) {
+        std::process::exit(cli::run(&args));
+    }
+    // 用 ImgZap 打开或拖到程序图标上的文件, 忽略 macOS 的 -psn_* 等其他参数
+    let open_paths = args
+        .iter()
+        .map(PathBuf::from)
+        .filter(|path| path.exists())
+        .collect::<Vec<_>>();
+
+    let config = Config::load();
+    i18n::set_language(config.language.unwrap_or_else(Language::system));
+    let (width, height) = config.window_size;
+    // 读取系统字体较慢, 启动时只读一次
+    let families = font::families();
+    let font = font::ui_font(config.ui_font.as_deref(), &families);
+    let boot = move || {
+        let open_files = open_paths.iter().cloned().map(Message::DropFile);
+        (
+            App::new(config.clone(), families.clone()),
+            Task::batch(open_files.map(Task::done)),
+        )
+    };
+    iced::application(boot, App::update, App::view)
         .subscription(App::subscription)
         .theme(|_| Theme::Dark)
         .title("ImgZap")
         .window(Settings {
             icon: load_icon(),
             position: iced::window::Position::Centered,
-            size: Size::new(720.0, 400.0),
+            size: Size::new(width, height),
             min_size: Some(Size::new(500.0, 310.0)),
+            // 关闭前保存设置
+            exit_on_close_request: false,
             ..Default::default()
         })
-        .default_font(Font::with_name("Microsoft YaHei UI"))
+        .default_font(font)
         .run()
 }
 
@@ -45,16 +113,179 @@ fn load_icon() -> Option<iced::window::Icon> {
 struct App {
     images: HashMap<PathBuf, (ImageFormatExt, bool)>,
     convert_img_format: HashMap<ImageFormatExt, bool>,
+    /// 格式列表的显示顺序
+    format_order: Vec<ImageFormatExt>,
     select_all_images: bool,
+    /// Shift 范围勾选的起点, 即上次点击的文件
+    selection_anchor: Option<PathBuf>,
+    modifiers: Modifiers,
+    convert_options: ConvertOptions,
+    reports: Vec<ConvertReport>,
+    /// 本次转换中每个文件的状态, 未参与转换的文件没有状态
+    statuses: HashMap<PathBuf, ConvertStatus>,
+    converting: bool,
+    /// 日志面板是否展开
+    show_log: bool,
+    svg_size_input: String,
+    svg_sizes_input: String,
+    svg_background_input: String,
+    matte_color_input: String,
+    /// 缩略图缓存, 加载中或加载失败为 None
+    thumbnails: HashMap<PathBuf, Option<Handle>>,
+    /// 等待解码缩略图的文件, 移除文件时一并删除, 后台线程会跳过不在其中的文件
+    pending_thumbnails: Arc<Mutex<HashSet<PathBuf>>>,
+    /// 原图尺寸, 随缩略图一起读取
+    dimensions: HashMap<PathBuf, (u32, u32)>,
+    /// 添加时读取的文件大小和修改时间, 用于排序
+    file_stats: HashMap<PathBuf, (u64, SystemTime)>,
+    sort_key: SortKey,
+    sort_descending: bool,
+    filter_input: String,
+    /// 只显示该格式的文件, None 为全部
+    format_filter: Option<ImageFormatExt>,
+    /// 经过筛选并排序后显示在列表中的文件, 列表、排序或筛选变化时重新计算
+    visible: Vec<PathBuf>,
+    /// 详情面板中显示的文件
+    selected_image: Option<PathBuf>,
+    /// 详情缓存, 读取失败时为错误信息
+    details: HashMap<PathBuf, Result<ImageDetails, String>>,
+    /// 对比预览的目标格式
+    preview_format: ImageFormatExt,
+    preview: Option<Result<Preview, String>>,
+    previewing: bool,
+    /// 对比视图中原图所占的比例
+    preview_split: f32,
+    preview_handle: Option<Handle>,
+    config: Config,
+    /// 已安装的字体, 供选择界面字体
+    font_families: Vec<UiFont>,
+    /// 是否装有自动选择时使用的中文字体
+    has_cjk_font: bool,
+    /// 最后应用或保存的预设的标识
+    active_preset: Option<String>,
+    preset_name_input: String,
+    /// 上次退出时的文件列表, 等待用户选择是否恢复
+    restore_session: Vec<PathBuf>,
+    /// 设置有改动, 等待延迟写入配置
+    save_scheduled: bool,
+}
+
+const THUMBNAIL_SIZE: u32 = 48;
+/// 设置改动后延迟写入配置, 拖动滑块等连续改动只写一次
+const CONFIG_SAVE_DELAY: Duration = Duration::from_secs(1);
+/// 同时解码缩略图的线程数, 不占满 CPU, 转换时界面仍能响应
+const THUMBNAIL_THREADS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SortKey {
+    #[default]
+    Name,
+    Path,
+    Size,
+    Format,
+    Dimensions,
+    Modified,
+}
+
+impl SortKey {
+    const ALL: [SortKey; 6] = [
+        SortKey::Name,
+        SortKey::Path,
+        SortKey::Size,
+        SortKey::Format,
+        SortKey::Dimensions,
+        SortKey::Modified,
+    ];
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            SortKey::Name => "按名称",
+            SortKey::Path => "按路径",
+            SortKey::Size => "按大小",
+            SortKey::Format => "按格式",
+            SortKey::Dimensions => "按尺寸",
+            SortKey::Modified => "按修改时间",
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConvertStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl fmt::Display for ConvertStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            ConvertStatus::Queued => "等待中",
+            ConvertStatus::Running => "转换中",
+            ConvertStatus::Done => "完成",
+            ConvertStatus::Failed => "失败",
+        }))
+    }
 }
 
-impl Default for App {
-    fn default() -> Self {
-        App {
+impl App {
+    fn new(config: Config, families: Vec<String>) -> Self {
+        let mut convert_img_format = ImageFormatExt::get_all();
+        for format in config.formats.iter() {
+            convert_img_format.insert(*format, true);
+        }
+        let mut app = App {
             images: HashMap::new(),
-            convert_img_format: ImageFormatExt::get_all(),
+            convert_img_format,
+            format_order: config.format_order(),
             select_all_images: false,
-        }
+            selection_anchor: None,
+            modifiers: Modifiers::default(),
+            reports: Vec::new(),
+            statuses: HashMap::new(),
+            converting: false,
+            show_log: false,
+            svg_size_input: String::new(),
+            svg_sizes_input: String::new(),
+            svg_background_input: String::new(),
+            matte_color_input: String::new(),
+            thumbnails: HashMap::new(),
+            pending_thumbnails: Arc::default(),
+            dimensions: HashMap::new(),
+            file_stats: HashMap::new(),
+            sort_key: SortKey::default(),
+            sort_descending: false,
+            filter_input: String::new(),
+            format_filter: None,
+            visible: Vec::new(),
+            selected_image: None,
+            details: HashMap::new(),
+            preview_format: ImageFormatExt::Jpeg,
+            preview: None,
+            previewing: false,
+            preview_split: 0.5,
+            preview_handle: None,
+            convert_options: ConvertOptions::default(),
+            restore_session: config
+                .last_session
+                .iter()
+                .filter(|path| path.is_file())
+                .cloned()
+                .collect(),
+            config: config.clone(),
+            has_cjk_font: font::candidate(&families).is_some(),
+            font_families: std::iter::once(UiFont::Auto)
+                .chain(families.into_iter().map(UiFont::Family))
+                .collect(),
+            active_preset: None,
+            preset_name_input: String::new(),
+            save_scheduled: false,
+        };
+        app.set_convert_options(config.convert);
+
+        app
     }
 }
 
@@ -62,7 +293,14 @@ impl Default for App {
 enum Message {
     Clear,
     ToggleImageItem(PathBuf),
+    RemoveImage(PathBuf),
+    RemoveChecked,
+    KeyPressed(keyboard::Key, Modifiers),
+    ModifiersChanged(Modifiers),
     ToggleImageFormatItem(ImageFormatExt, bool),
+    /// 在格式列表中上移 (true) 或下移一位
+    MoveFormat(ImageFormatExt, bool),
+    LanguageSelected(Language),
     OpenFileDialog,
     OpenFolderDialog,
     FileSelected(Option<Vec<FileHandle>>),
@@ -70,9 +308,130 @@ enum Message {
     SelectAllImage(bool),
     DropFile(PathBuf),
     ConvertImage,
+    ConvertProgress(ConvertEvent),
+    ConvertFinished(Vec<ConvertReport>),
+    ToggleLog,
+    WindowResized(Size),
+    CloseRequested,
+    /// 延迟写入配置的时间到了
+    SaveConfig,
+    RestoreSession,
+    DismissSession,
+    OpenRecentFolder(String),
+    SelectOutputDir,
+    OutputDirSelected(Option<FileHandle>),
+    ClearOutputDir,
+    NameTemplateChanged(String),
+    PresetSelected(Preset),
+    UiFontSelected(UiFont),
+    PresetNameChanged(String),
+    SavePreset,
+    DeletePreset,
+    OpenFolder(PathBuf),
+    CopyText(String),
+    ThumbnailLoaded(PathBuf, Option<(Handle, (u32, u32))>),
+    SortKeySelected(SortKey),
+    ToggleSortOrder,
+    FilterChanged(String),
+    FormatFilterSelected(Option<ImageFormatExt>),
+    SelectFormat(ImageFormatExt),
+    InvertSelection,
+    SelectImage(PathBuf),
+    /// 点击列表中的缩略图, 按住 Ctrl/Cmd 或 Shift 时改为勾选
+    ImageClicked(PathBuf),
+    CloseDetails,
+    DetailsLoaded(PathBuf, Result<ImageDetails, String>),
+    PreviewFormatSelected(ImageFormatExt),
+    PreviewImage,
+    PreviewFinished(Result<Preview, String>),
+    PreviewSplitChanged(f32),
+    ClosePreview,
+    QualityChanged(u8),
+    SvgSizeKindSelected(SvgSizeKind),
+    SvgSizeChanged(String),
+    SvgFitSelected(SvgFit),
+    SvgSizesChanged(String),
+    SvgPaddingChanged(u32),
+    SvgBackgroundChanged(String),
+    AddSvgFontDir,
+    SvgFontDirSelected(Option<FileHandle>),
+    RemoveSvgFontDir(usize),
+    SvgDefaultFontChanged(String),
+    SvgBlockExternalToggled(bool),
+    SvgOptimizeChanged(SvgOptimize),
+    TracePresetSelected(TracePreset),
+    TraceChanged(TraceOptions),
+    ResizeChanged(ResizeOptions),
+    OrientationSelected(OrientationMode),
+    MetadataPolicySelected(MetadataPolicy),
+    CompressChanged(CompressOptions),
+    PreserveTimestampsToggled(bool),
+    DitherToggled(bool),
+    MatteColorChanged(String),
+    MatteChanged(MatteOptions),
+    ColorTargetSelected(ColorTarget),
+    SelectIccProfile,
+    IccProfileSelected(Option<FileHandle>),
+    TiffCompressionSelected(TiffCompression),
+    MergeTiff,
+    MergeTiffSelected(Option<FileHandle>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+impl Message {
+    /// 改变了会写入配置的设置或文件列表
+    fn changes_config(&self) -> bool {
+        matches!(
+            self,
+            Message::Clear
+                | Message::RemoveImage(_)
+                | Message::RemoveChecked
+                | Message::ToggleImageFormatItem(..)
+                | Message::MoveFormat(..)
+                | Message::LanguageSelected(_)
+                | Message::FileSelected(_)
+                | Message::FolderSelected(_)
+                | Message::DropFile(_)
+                | Message::WindowResized(_)
+                | Message::RestoreSession
+                | Message::DismissSession
+                | Message::OpenRecentFolder(_)
+                | Message::OutputDirSelected(_)
+                | Message::ClearOutputDir
+                | Message::NameTemplateChanged(_)
+                | Message::PresetSelected(_)
+                | Message::UiFontSelected(_)
+                | Message::SavePreset
+                | Message::DeletePreset
+                | Message::QualityChanged(_)
+                | Message::SvgSizeKindSelected(_)
+                | Message::SvgSizeChanged(_)
+                | Message::SvgFitSelected(_)
+                | Message::SvgSizesChanged(_)
+                | Message::SvgPaddingChanged(_)
+                | Message::SvgBackgroundChanged(_)
+                | Message::SvgFontDirSelected(_)
+                | Message::RemoveSvgFontDir(_)
+                | Message::SvgDefaultFontChanged(_)
+                | Message::SvgBlockExternalToggled(_)
+                | Message::SvgOptimizeChanged(_)
+                | Message::TracePresetSelected(_)
+                | Message::TraceChanged(_)
+                | Message::ResizeChanged(_)
+                | Message::OrientationSelected(_)
+                | Message::MetadataPolicySelected(_)
+                | Message::CompressChanged(_)
+                | Message::PreserveTimestampsToggled(_)
+                | Message::DitherToggled(_)
+                | Message::MatteColorChanged(_)
+                | Message::MatteChanged(_)
+                | Message::ColorTargetSelected(_)
+                | Message::IccProfileSelected(_)
+                | Message::TiffCompressionSelected(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ImageFormatExt {
     Png,
     Jpeg,
@@ -81,21 +440,32 @@ pub enum ImageFormatExt {
     Bmp,
     Ico,
     Avif,
+    Hdr,
+    Exr,
     Svg,
+    AppIcon,
 }
 
 impl ImageFormatExt {
+    const ALL: [ImageFormatExt; 11] = [
+        ImageFormatExt::Png,
+        ImageFormatExt::Jpeg,
+        ImageFormatExt::WebP,
+        ImageFormatExt::Tiff,
+        ImageFormatExt::Bmp,
+        ImageFormatExt::Ico,
+        ImageFormatExt::Avif,
+        ImageFormatExt::Hdr,
+        ImageFormatExt::Exr,
+        ImageFormatExt::Svg,
+        ImageFormatExt::AppIcon,
+    ];
+
     fn get_all() -> HashMap<ImageFormatExt, bool> {
-        let mut vec = HashMap::new();
-        vec.insert(ImageFormatExt::Png, false);
-        vec.insert(ImageFormatExt::Jpeg, false);
-        vec.insert(ImageFormatExt::WebP, false);
-        vec.insert(ImageFormatExt::Tiff, false);
-        vec.insert(ImageFormatExt::Bmp, false);
-        vec.insert(ImageFormatExt::Ico, false);
-        vec.insert(ImageFormatExt::Avif, false);
-        vec.insert(ImageFormatExt::Svg, false);
-        vec
+        ImageFormatExt::ALL
+            .into_iter()
+            .map(|format| (format, false))
+            .collect()
     }
 
     fn get_format_from_mime(mime: &str) -> Option<ImageFormatExt> {
@@ -109,6 +479,8 @@ impl ImageFormatExt {
             "image/tiff" => Some(ImageFormatExt::Tiff),
             "image/webp" => Some(ImageFormatExt::WebP),
             "image/avif" => Some(ImageFormatExt::Avif),
+            "image/vnd.radiance" => Some(ImageFormatExt::Hdr),
+            "image/x-exr" | "image/aces" => Some(ImageFormatExt::Exr),
             _ => None,
         }
     }
@@ -122,7 +494,10 @@ impl ImageFormatExt {
             ImageFormatExt::Bmp => "BMP",
             ImageFormatExt::Ico => "ICO",
             ImageFormatExt::Avif => "AVIF",
+            ImageFormatExt::Hdr => "HDR",
+            ImageFormatExt::Exr => "EXR",
             ImageFormatExt::Svg => "SVG",
+            ImageFormatExt::AppIcon => "APP ICON",
         }
     }
 
@@ -138,40 +513,202 @@ impl ImageFormatExt {
             ImageFormatExt::Tiff => Some(image::ImageFormat::Tiff),
             ImageFormatExt::Bmp => Some(image::ImageFormat::Bmp),
             ImageFormatExt::Avif => Some(image::ImageFormat::Avif),
+            ImageFormatExt::Hdr => Some(image::ImageFormat::Hdr),
+            ImageFormatExt::Exr => Some(image::ImageFormat::OpenExr),
             ImageFormatExt::Ico => None,
             ImageFormatExt::Svg => None,
+            ImageFormatExt::AppIcon => None,
         }
     }
 }
 
+impl fmt::Display for ImageFormatExt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.get_name())
+    }
+}
+
 impl App {
     fn update(&mut self, event: Message) -> Task<Message> {
+        let changes_config = event.changes_config();
+        let task = self.handle(event);
+        if !changes_config || self.save_scheduled {
+            return task;
+        }
+
+        self.save_scheduled = true;
+        let (sender, receiver) = iced::futures::channel::oneshot::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(CONFIG_SAVE_DELAY);
+            let _ = sender.send(());
+        });
+        Task::batch([task, Task::perform(receiver, |_| Message::SaveConfig)])
+    }
+
+    fn handle(&mut self, event: Message) -> Task<Message> {
         match event {
             Message::SelectAllImage(should_select) => {
-                if self.select_all_images.ne(&should_select) {
-                    self.select_all_images = should_select;
-                    self.images
-                        .iter_mut()
-                        .for_each(|(_, (_, c))| *c = should_select);
-                }
+                self.select_all_images = should_select;
+                self.check_visible(|_, _| should_select);
+
+                Task::none()
+            }
+            Message::SelectFormat(image_format) => {
+                self.check_visible(|format, is_checked| is_checked || *format == image_format);
+
+                Task::none()
+            }
+            Message::InvertSelection => {
+                self.check_visible(|_, is_checked| !is_checked);
+
+                Task::none()
+            }
+            Message::SortKeySelected(sort_key) => {
+                self.sort_key = sort_key;
+                self.refresh_visible();
+
+                Task::none()
+            }
+            Message::ToggleSortOrder => {
+                self.sort_descending = !self.sort_descending;
+                self.refresh_visible();
+
+                Task::none()
+            }
+            Message::FilterChanged(filter) => {
+                self.filter_input = filter;
+                self.refresh_visible();
+
+                Task::none()
+            }
+            Message::FormatFilterSelected(format_filter) => {
+                self.format_filter = format_filter;
+                self.refresh_visible();
 
                 Task::none()
             }
             Message::ToggleImageItem(key) => {
-                if let Some((_, is_check)) = self.images.get_mut(&key) {
-                    *is_check = !*is_check;
+                let Some(should_check) = self.images.get(&key).map(|(_, c)| !c) else {
+                    return Task::none();
+                };
+                // 按住 Shift 时, 上次点击的文件到当前文件之间都设为同一状态
+                let range = self
+                    .selection_anchor
+                    .as_ref()
+                    .filter(|_| self.modifiers.shift())
+                    .and_then(|anchor| {
+                        let visible = self.visible_images();
+                        let start = visible.iter().position(|p| p == anchor)?;
+                        let end = visible.iter().position(|p| *p == key)?;
+                        Some(visible[start.min(end)..=start.max(end)].to_vec())
+                    })
+                    .unwrap_or_else(|| vec![key.clone()]);
+                for path in range {
+                    if let Some((_, is_check)) = self.images.get_mut(&path) {
+                        *is_check = should_check;
+                    }
                 }
+                self.selection_anchor = Some(key);
+
+                Task::none()
+            }
+            // 转换在后台进行, 过程中不允许移除文件
+            Message::RemoveImage(_) | Message::RemoveChecked if self.converting => Task::none(),
+            Message::RemoveImage(path) => {
+                self.remove_images(&[path]);
+
+                Task::none()
+            }
+            Message::RemoveChecked => {
+                let checked = self
+                    .images
+                    .iter()
+                    .filter(|(_, (_, is_checked))| *is_checked)
+                    .map(|(path, _)| path.clone())
+                    .collect::<Vec<_>>();
+                self.remove_images(&checked);
 
                 Task::none()
             }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+
+                Task::none()
+            }
+            Message::KeyPressed(key, modifiers) => {
+                use keyboard::{Key, key::Named};
+                let message = match key.as_ref() {
+                    Key::Named(Named::Delete) if !self.converting => Some(Message::RemoveChecked),
+                    Key::Named(Named::Enter) if modifiers.command() && !self.converting => {
+                        Some(Message::ConvertImage)
+                    }
+                    Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("o") => {
+                        Some(if modifiers.shift() {
+                            Message::OpenFolderDialog
+                        } else {
+                            Message::OpenFileDialog
+                        })
+                    }
+                    Key::Character(c) if modifiers.command() && c.eq_ignore_ascii_case("a") => {
+                        Some(Message::SelectAllImage(!self.select_all_images))
+                    }
+                    _ => None,
+                };
+
+                match message {
+                    Some(message) => self.update(message),
+                    None => Task::none(),
+                }
+            }
             Message::ToggleImageFormatItem(image_format, should_convert) => {
                 self.convert_img_format
                     .insert(image_format, !should_convert);
 
                 Task::none()
             }
+            Message::MoveFormat(image_format, up) => {
+                if let Some(index) = self.format_order.iter().position(|f| *f == image_format) {
+                    let target = if up {
+                        index.checked_sub(1)
+                    } else {
+                        Some(index + 1)
+                    };
+                    if let Some(target) = target.filter(|t| *t < self.format_order.len()) {
+                        self.format_order.swap(index, target);
+                    }
+                }
+
+                Task::none()
+            }
+            Message::LanguageSelected(language) => {
+                i18n::set_language(language);
+                self.config.language = Some(language);
+
+                Task::none()
+            }
+            Message::UiFontSelected(ui_font) => {
+                self.config.ui_font = match ui_font {
+                    UiFont::Auto => None,
+                    UiFont::Family(family) => Some(family),
+                };
+
+                Task::none()
+            }
             Message::Clear => {
                 self.images.clear();
+                self.visible.clear();
+                self.thumbnails.clear();
+                self.pending_thumbnails
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .clear();
+                self.dimensions.clear();
+                self.file_stats.clear();
+                self.details.clear();
+                self.statuses.clear();
+                self.reports.clear();
+                self.selected_image = None;
+                self.selection_anchor = None;
                 self.select_all_images = false;
                 Task::none()
             }
@@ -182,7 +719,8 @@ impl App {
                         .for_each(|file_handle| self.check_image(file_handle.path()))
                 }
 
-                Task::none()
+                self.refresh_visible();
+                self.load_thumbnails()
             }
             Message::FolderSelected(folders_handle) => {
                 if let Some(folders_handle) = folders_handle {
@@ -191,15 +729,129 @@ impl App {
                     })
                 }
 
+                self.refresh_visible();
+                self.load_thumbnails()
+            }
+            Message::ImageClicked(path) => {
+                // Ctrl/Cmd 点击切换这个文件的勾选, Shift 点击勾选范围, 与复选框相同
+                if self.modifiers.command() || self.modifiers.shift() {
+                    self.update(Message::ToggleImageItem(path))
+                } else {
+                    self.update(Message::SelectImage(path))
+                }
+            }
+            Message::SelectImage(path) => {
+                self.selected_image = Some(path.clone());
+                let Some((format, _)) = self.images.get(&path).copied() else {
+                    return Task::none();
+                };
+                if self.details.contains_key(&path) {
+                    return Task::none();
+                }
+
+                let (sender, receiver) = iced::futures::channel::oneshot::channel();
+                let details_path = path.clone();
+                std::thread::spawn(move || {
+                    let details =
+                        details::read(&details_path, &format).map_err(|e| format!("{e:#}"));
+                    let _ = sender.send(details);
+                });
+
+                Task::perform(receiver, move |details| {
+                    Message::DetailsLoaded(
+                        path.clone(),
+                        details.unwrap_or_else(|e| Err(e.to_string())),
+                    )
+                })
+            }
+            Message::PreviewFormatSelected(format) => {
+                self.preview_format = format;
+
+                Task::none()
+            }
+            Message::PreviewImage => {
+                let Some(path) = self.selected_image.clone() else {
+                    return Task::none();
+                };
+                let Some((image_format, _)) = self.images.get(&path).copied() else {
+                    return Task::none();
+                };
+                let preview_format = self.preview_format;
+                let convert_options = self.convert_options.clone();
+                self.previewing = true;
+
+                let (sender, receiver) = iced::futures::channel::oneshot::channel();
+                std::thread::spawn(move || {
+                    let preview =
+                        convert::preview(&path, &image_format, &preview_format, &convert_options)
+                            .map_err(|e| format!("{e:#}"));
+                    let _ = sender.send(preview);
+                });
+
+                Task::perform(receiver, |preview| {
+                    Message::PreviewFinished(preview.unwrap_or_else(|e| Err(e.to_string())))
+                })
+            }
+            Message::PreviewFinished(preview) => {
+                self.previewing = false;
+                self.preview = Some(preview);
+                self.update_preview_handle();
+
+                Task::none()
+            }
+            Message::PreviewSplitChanged(split) => {
+                self.preview_split = split;
+                self.update_preview_handle();
+
+                Task::none()
+            }
+            Message::ClosePreview => {
+                self.preview = None;
+                self.preview_handle = None;
+
+                Task::none()
+            }
+            Message::QualityChanged(quality) => {
+                self.convert_options.quality = quality;
+
+                Task::none()
+            }
+            Message::CloseDetails => {
+                self.selected_image = None;
+
+                Task::none()
+            }
+            Message::DetailsLoaded(path, details) => {
+                if self.images.contains_key(&path) {
+                    self.details.insert(path, details);
+                }
+
+                Task::none()
+            }
+            Message::ThumbnailLoaded(path, thumbnail) => {
+                if self.images.contains_key(&path) {
+                    let handle = thumbnail.map(|(handle, dimensions)| {
+                        self.dimensions.insert(path.clone(), dimensions);
+                        handle
+                    });
+                    self.thumbnails.insert(path, handle);
+                    // 尺寸随缩略图读出, 按尺寸排序时需要重新排序
+                    if self.sort_key == SortKey::Dimensions {
+                        self.refresh_visible();
+                    }
+                }
+
                 Task::none()
             }
             Message::OpenFileDialog => Task::perform(
-                AsyncFileDialog::new().set_title("选择文件").pick_files(),
+                AsyncFileDialog::new()
+                    .set_title(tr("选择文件"))
+                    .pick_files(),
                 Message::FileSelected,
             ),
             Message::OpenFolderDialog => Task::perform(
                 AsyncFileDialog::new()
-                    .set_title("选择文件夹")
+                    .set_title(tr("选择文件夹"))
                     .pick_folders(),
                 Message::FolderSelected,
             ),
@@ -210,55 +862,644 @@ impl App {
                     self.check_image(&path)
                 }
 
-                Task::none()
+                self.refresh_visible();
+                self.load_thumbnails()
             }
             Message::ConvertImage => {
+                self.reports.clear();
+                self.statuses = self
+                    .images
+                    .iter()
+                    .filter(|(_, (_, is_checked))| *is_checked)
+                    .map(|(path, _)| (path.clone(), ConvertStatus::Queued))
+                    .collect();
+                self.converting = true;
+
                 let images = self.images.clone();
                 let convert_img_format = self.convert_img_format.clone();
+                let convert_options = self.convert_options.clone();
+                let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+                std::thread::spawn(move || {
+                    convert::image_to_other(&images, &convert_img_format, &convert_options, |e| {
+                        let _ = sender.unbounded_send(e);
+                    });
+                });
+
+                Task::run(receiver, Message::ConvertProgress)
+                    .chain(Task::done(Message::ConvertFinished(Vec::new())))
+            }
+            Message::ConvertProgress(ConvertEvent::Started(path)) => {
+                // 转换过程中可能已从列表移除
+                if self.images.contains_key(&path) {
+                    self.statuses.insert(path, ConvertStatus::Running);
+                }
+
+                Task::none()
+            }
+            Message::ConvertProgress(ConvertEvent::Finished(path, reports)) => {
+                let status = if reports.iter().any(|report| report.error.is_some()) {
+                    ConvertStatus::Failed
+                } else {
+                    ConvertStatus::Done
+                };
+                if self.images.contains_key(&path) {
+                    self.statuses.insert(path, status);
+                }
+                self.reports.extend(reports);
+
+                Task::none()
+            }
+            Message::ConvertFinished(reports) => {
+                self.converting = false;
+                self.reports.extend(reports);
+                // 有错误时自动展开日志
+                self.show_log |= self.reports.iter().any(|report| report.error.is_some());
+
+                Task::none()
+            }
+            Message::WindowResized(size) => {
+                self.config.window_size = (size.width, size.height);
+
+                Task::none()
+            }
+            Message::CloseRequested => {
+                self.save_config();
+
+                iced::exit()
+            }
+            Message::SaveConfig => {
+                self.save_scheduled = false;
+                self.save_config();
+
+                Task::none()
+            }
+            Message::RestoreSession => {
+                for path in std::mem::take(&mut self.restore_session) {
+                    self.check_image(&path);
+                }
+
+                self.refresh_visible();
+                self.load_thumbnails()
+            }
+            Message::DismissSession => {
+                self.restore_session.clear();
+
+                Task::none()
+            }
+            Message::OpenRecentFolder(folder) => {
+                let folder = PathBuf::from(folder);
+                if folder.is_dir() {
+                    self.get_image_file_from_folder(&folder);
+                } else {
+                    self.config.recent_folders.retain(|f| *f != folder);
+                }
+
+                self.refresh_visible();
+                self.load_thumbnails()
+            }
+            Message::SelectOutputDir => Task::perform(
+                AsyncFileDialog::new()
+                    .set_title(tr("选择输出文件夹"))
+                    .pick_folder(),
+                Message::OutputDirSelected,
+            ),
+            Message::OutputDirSelected(folder_handle) => {
+                if let Some(folder_handle) = folder_handle {
+                    self.convert_options.output_dir = Some(folder_handle.path().to_path_buf());
+                }
+
+                Task::none()
+            }
+            Message::ClearOutputDir => {
+                self.convert_options.output_dir = None;
+
+                Task::none()
+            }
+            Message::NameTemplateChanged(template) => {
+                self.convert_options.name_template = template;
+
+                Task::none()
+            }
+            Message::PresetSelected(preset) => {
+                for (format, should_convert) in self.convert_img_format.iter_mut() {
+                    *should_convert = preset.formats.contains(format);
+                }
+                self.set_convert_options(preset.options);
+                self.preset_name_input = preset.name;
+                self.active_preset = Some(preset.id);
+
+                Task::none()
+            }
+            Message::PresetNameChanged(name) => {
+                self.preset_name_input = name;
+
+                Task::none()
+            }
+            Message::SavePreset => {
+                let name = self.preset_name_input.trim().to_string();
+                // 与已有预设同名时覆盖它, 包括其他语言的内置预设名称
+                let id = preset::all(&self.config.presets)
+                    .into_iter()
+                    .find(|preset| preset.matches(&name))
+                    .map_or_else(|| name.clone(), |preset| preset.id);
+                let preset = Preset {
+                    id: id.clone(),
+                    name,
+                    formats: ImageFormatExt::ALL
+                        .into_iter()
+                        .filter(|format| self.convert_img_format.get(format) == Some(&true))
+                        .collect(),
+                    options: self.convert_options.clone(),
+                };
+                match self.config.presets.iter_mut().find(|p| p.id == id) {
+                    Some(saved) => *saved = preset,
+                    None => self.config.presets.push(preset),
+                }
+                self.active_preset = Some(id);
+                // 立即写入, 命令行也能使用
+                self.save_config();
+
+                Task::none()
+            }
+            Message::DeletePreset => {
+                if let Some(id) = self.active_preset.take() {
+                    self.config.presets.retain(|preset| preset.id != id);
+                    self.save_config();
+                }
+
+                Task::none()
+            }
+            Message::ToggleLog => {
+                self.show_log = !self.show_log;
+
+                Task::none()
+            }
+            Message::OpenFolder(path) => {
+                open_folder(&path);
+
+                Task::none()
+            }
+            Message::CopyText(text) => iced::clipboard::write(text),
+            Message::SvgSizeKindSelected(kind) => {
+                if kind != self.convert_options.svg.size.kind() {
+                    let size = SvgSize::parse(kind, &self.svg_size_input).unwrap_or(match kind {
+                        SvgSizeKind::Original => SvgSize::Original,
+                        SvgSizeKind::Width => SvgSize::Width(256),
+                        SvgSizeKind::Height => SvgSize::Height(256),
+                        SvgSizeKind::Exact => SvgSize::Exact(256, 256),
+                        SvgSizeKind::Scale => SvgSize::Scale(1.0),
+                        SvgSizeKind::Dpi => SvgSize::Dpi(96.0),
+                    });
+                    self.svg_size_input = size.to_string();
+                    self.convert_options.svg.size = size;
+                }
+
+                Task::none()
+            }
+            Message::SvgSizeChanged(input) => {
+                let kind = self.convert_options.svg.size.kind();
+                if let Some(size) = SvgSize::parse(kind, &input) {
+                    self.convert_options.svg.size = size;
+                }
+                self.svg_size_input = input;
+
+                Task::none()
+            }
+            Message::SvgFitSelected(fit) => {
+                self.convert_options.svg.fit = fit;
+
+                Task::none()
+            }
+            Message::SvgSizesChanged(input) => {
+                if let Some(sizes) = SvgSize::parse_list(&input) {
+                    self.convert_options.svg.sizes = sizes;
+                }
+                self.svg_sizes_input = input;
+
+                Task::none()
+            }
+            Message::SvgPaddingChanged(padding) => {
+                self.convert_options.svg.padding = padding;
+
+                Task::none()
+            }
+            Message::SvgBackgroundChanged(input) => {
+                self.convert_options.svg.background = svg::parse_hex_color(&input);
+                self.svg_background_input = input;
+
+                Task::none()
+            }
+            Message::AddSvgFontDir => Task::perform(
+                AsyncFileDialog::new()
+                    .set_title(tr("选择字体文件夹"))
+                    .pick_folder(),
+                Message::SvgFontDirSelected,
+            ),
+            Message::SvgFontDirSelected(folder_handle) => {
+                if let Some(folder_handle) = folder_handle {
+                    let font_dirs = &mut self.convert_options.svg.font_dirs;
+                    let dir = folder_handle.path().to_path_buf();
+                    if !font_dirs.contains(&dir) {
+                        font_dirs.push(dir);
+                    }
+                }
+
+                Task::none()
+            }
+            Message::RemoveSvgFontDir(index) => {
+                if index < self.convert_options.svg.font_dirs.len() {
+                    self.convert_options.svg.font_dirs.remove(index);
+                }
+
+                Task::none()
+            }
+            Message::SvgDefaultFontChanged(family) => {
+                self.convert_options.svg.default_font_family = family;
+
+                Task::none()
+            }
+            Message::SvgBlockExternalToggled(block_external) => {
+                self.convert_options.svg.block_external = block_external;
+
+                Task::none()
+            }
+            Message::SvgOptimizeChanged(optimize) => {
+                self.convert_options.svg.optimize = optimize;
+
+                Task::none()
+            }
+            Message::TracePresetSelected(preset) => {
+                if preset != TracePreset::Custom {
+                    self.convert_options.trace = preset.options();
+                }
+
+                Task::none()
+            }
+            Message::ResizeChanged(resize) => {
+                self.convert_options.resize = resize;
+
+                Task::none()
+            }
+            Message::OrientationSelected(orientation) => {
+                self.convert_options.orientation = orientation;
+
+                Task::none()
+            }
+            Message::MetadataPolicySelected(policy) => {
+                self.convert_options.metadata = policy;
+
+                Task::none()
+            }
+            Message::CompressChanged(compress) => {
+                self.convert_options.compress = compress;
+
+                Task::none()
+            }
+            Message::PreserveTimestampsToggled(preserve_timestamps) => {
+                self.convert_options.preserve_timestamps = preserve_timestamps;
+
+                Task::none()
+            }
+            Message::DitherToggled(dither) => {
+                self.convert_options.dither = dither;
+
+                Task::none()
+            }
+            Message::MatteColorChanged(input) => {
+                if let Some([r, g, b, _]) = svg::parse_hex_color(&input) {
+                    self.convert_options.matte.color = [r, g, b];
+                }
+                self.matte_color_input = input;
+
+                Task::none()
+            }
+            Message::MatteChanged(matte) => {
+                self.convert_options.matte = matte;
+
+                Task::none()
+            }
+            Message::ColorTargetSelected(target) => {
+                self.convert_options.color.target = target;
+
+                Task::none()
+            }
+            Message::SelectIccProfile => Task::perform(
+                AsyncFileDialog::new()
+                    .set_title(tr("选择 ICC 配置文件"))
+                    .add_filter("ICC", &["icc", "icm"])
+                    .pick_file(),
+                Message::IccProfileSelected,
+            ),
+            Message::IccProfileSelected(file_handle) => {
+                if let Some(file_handle) = file_handle {
+                    self.convert_options.color.profile_path =
+                        Some(file_handle.path().to_path_buf());
+                }
+
+                Task::none()
+            }
+            Message::TiffCompressionSelected(compression) => {
+                self.convert_options.tiff = compression;
+
+                Task::none()
+            }
+            Message::MergeTiff => Task::perform(
+                AsyncFileDialog::new()
+                    .set_title(tr("保存多页 TIFF"))
+                    .add_filter("TIFF", &["tiff", "tif"])
+                    .set_file_name("merged.tiff")
+                    .save_file(),
+                Message::MergeTiffSelected,
+            ),
+            Message::MergeTiffSelected(file_handle) => {
+                let Some(file_handle) = file_handle else {
+                    return Task::none();
+                };
+                let output_path = file_handle.path().to_path_buf();
+                self.converting = true;
+                let inputs = self.checked_in_order();
+                let convert_options = self.convert_options.clone();
+                let (sender, receiver) = iced::futures::channel::oneshot::channel();
                 std::thread::spawn(move || {
-                    convert::image_to_other(&images, &convert_img_format);
+                    let report = convert::merge_to_tiff(&inputs, &output_path, &convert_options);
+                    let _ = sender.send(report);
                 });
 
+                Task::perform(receiver, |report| {
+                    Message::ConvertFinished(report.into_iter().collect())
+                })
+            }
+            Message::TraceChanged(trace) => {
+                self.convert_options.trace = TraceOptions {
+                    preset: TracePreset::Custom,
+                    ..trace
+                };
+
                 Task::none()
             }
         }
     }
 
     fn view(&self) -> Element<'_, Message> {
-        let select_files_button = button("选择文件")
+        let select_files_button = button(tr("选择文件"))
             .on_press(Message::OpenFileDialog)
             .width(iced::Length::Fill);
 
-        let select_folders_button = button("选择文件夹")
+        let select_folders_button = button(tr("选择文件夹"))
             .on_press(Message::OpenFolderDialog)
             .width(iced::Length::Fill);
 
-        let clear_button = button("清空")
+        let clear_button = button(tr("清空"))
             .on_press(Message::Clear)
             .width(iced::Length::Fill);
 
-        let convert_button = button("转换")
-            .on_press(Message::ConvertImage)
+        let hidden_checked = self.hidden_checked();
+        let convert_button = button(text(if self.converting {
+            tr("转换中...").to_string()
+        } else if hidden_checked > 0 {
+            trf("转换 (含 {} 个被筛选隐藏的文件)", &[&hidden_checked])
+        } else {
+            tr("转换").to_string()
+        }))
+        .on_press_maybe((!self.converting).then_some(Message::ConvertImage))
+        .width(iced::Length::Fill);
+
+        let merge_tiff_button = button(tr("合并为 TIFF"))
+            .on_press_maybe(
+                (!self.converting && self.images.values().any(|(_, is_checked)| *is_checked))
+                    .then_some(Message::MergeTiff),
+            )
             .width(iced::Length::Fill);
 
+        let present_formats = ImageFormatExt::ALL
+            .into_iter()
+            .filter(|format| self.images.values().any(|(f, _)| f == format))
+            .collect::<Vec<_>>();
+        let format_filter_button = |label: String, format_filter: Option<ImageFormatExt>| {
+            button(text(label))
+                .on_press(Message::FormatFilterSelected(format_filter))
+                .style(if self.format_filter == format_filter {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .into()
+        };
+        let format_filters = iced::widget::Row::with_children(
+            std::iter::once(format_filter_button(tr("全部").to_string(), None)).chain(
+                present_formats.iter().map(|format| {
+                    let count = self.images.values().filter(|(f, _)| f == format).count();
+                    format_filter_button(format!("{format} ({count})"), Some(*format))
+                }),
+            ),
+        )
+        .spacing(5)
+        .wrap();
+
+        let recent_folders = self
+            .config
+            .recent_folders
+            .iter()
+            .map(|folder| folder.display().to_string())
+            .collect::<Vec<_>>();
+        let mut list_controls = column![
+            row![
+                text_input(tr("搜索文件名或路径"), &self.filter_input)
+                    .on_input(Message::FilterChanged)
+                    .width(iced::Length::Fill),
+                pick_list(recent_folders, None::<String>, Message::OpenRecentFolder)
+                    .placeholder(tr("最近的文件夹"))
+                    .width(160),
+                pick_list(SortKey::ALL, Some(self.sort_key), Message::SortKeySelected),
+                button(if self.sort_descending {
+                    tr("降序")
+                } else {
+                    tr("升序")
+                })
+                .on_press(Message::ToggleSortOrder),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+            format_filters,
+        ]
+        .spacing(10);
+        if !self.restore_session.is_empty() {
+            list_controls = list_controls.push(
+                row![
+                    text(trf(
+                        "上次退出时列表中有 {} 个文件",
+                        &[&self.restore_session.len()]
+                    ))
+                    .width(iced::Length::Fill),
+                    button(tr("恢复")).on_press(Message::RestoreSession),
+                    button(tr("忽略"))
+                        .on_press(Message::DismissSession)
+                        .style(button::secondary),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+            );
+        }
+
         let mut images_list = Column::new()
             .push(
-                checkbox("< 选 择 所 有 >", self.select_all_images)
-                    .style(checkbox::success)
-                    .on_toggle(Message::SelectAllImage),
+                row![
+                    checkbox(tr("< 选 择 所 有 >"), self.select_all_images)
+                        .style(checkbox::success)
+                        .on_toggle(Message::SelectAllImage)
+                        .width(iced::Length::Fill),
+                    pick_list(
+                        present_formats,
+                        None::<ImageFormatExt>,
+                        Message::SelectFormat
+                    )
+                    .placeholder(tr("按格式选择")),
+                    button(tr("反选")).on_press(Message::InvertSelection),
+                    button(tr("移除选中")).on_press_maybe(
+                        (!self.converting
+                            && self.images.values().any(|(_, is_checked)| *is_checked))
+                        .then_some(Message::RemoveChecked)
+                    ),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
             )
             .spacing(10);
 
-        for (path, (_mime, is_checked)) in self.images.iter() {
-            images_list = images_list.push(
+        let thumbnail_size = iced::Length::Fixed(THUMBNAIL_SIZE as f32);
+        for path in self.visible_images() {
+            let (_, is_checked) = &self.images[path];
+            let is_selected = self.selected_image.as_ref() == Some(path);
+            let thumbnail: Element<'_, Message> = match self.thumbnails.get(path) {
+                Some(Some(handle)) => iced::widget::image(handle.clone())
+                    .width(thumbnail_size)
+                    .height(thumbnail_size)
+                    .into(),
+                _ => container(text(""))
+                    .width(thumbnail_size)
+                    .height(thumbnail_size)
+                    .style(container::bordered_box)
+                    .into(),
+            };
+            let mut entry = row![
+                button(thumbnail)
+                    .on_press(Message::ImageClicked(path.clone()))
+                    .padding(2)
+                    .style(if is_selected {
+                        button::primary
+                    } else {
+                        button::text
+                    }),
                 checkbox(
                     path.file_name()
                         .and_then(OsStr::to_str)
-                        .unwrap_or("<未知文件名>"),
+                        .unwrap_or(tr("<未知文件名>")),
                     *is_checked,
                 )
-                .on_toggle(|_| Message::ToggleImageItem(path.into())),
+                .on_toggle(|_| Message::ToggleImageItem(path.into()))
+                .width(iced::Length::Fill),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center);
+            if let Some(status) = self.statuses.get(path) {
+                entry = entry.push(text(status.to_string()).style(match status {
+                    ConvertStatus::Done => text::success,
+                    ConvertStatus::Failed => text::danger,
+                    _ => text::default,
+                }));
+            }
+            entry = entry.push(
+                button("x")
+                    .on_press_maybe((!self.converting).then(|| Message::RemoveImage(path.clone())))
+                    .style(button::text),
             );
+
+            let mut entry = column![entry].spacing(5);
+            let outputs = self
+                .reports
+                .iter()
+                .filter(|report| &report.input_path == path)
+                .flat_map(|report| report.outputs.iter())
+                .collect::<Vec<_>>();
+            for (output_path, size) in outputs.iter() {
+                let file_name = output_path
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .unwrap_or(tr("<未知文件名>"));
+                entry = entry.push(text(format!(
+                    "-> {file_name} ({})",
+                    details::format_size(*size)
+                )));
+            }
+            if let Some((output_path, _)) = outputs.first()
+                && let Some(folder) = output_path.parent()
+            {
+                entry = entry.push(
+                    button(tr("打开输出文件夹"))
+                        .on_press(Message::OpenFolder(folder.to_path_buf()))
+                        .style(button::secondary),
+                );
+            }
+            images_list = images_list.push(entry);
+        }
+
+        let mut log = Column::new().spacing(5);
+        for report in self.reports.iter() {
+            let file_name = report
+                .input_path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .unwrap_or(tr("<未知文件名>"));
+            let format = report.convert_format.get_name();
+            if let Some(error) = &report.error {
+                let message = format!("{file_name} -> {format}: {error}");
+                log = log.push(
+                    row![
+                        text(message.clone())
+                            .style(text::danger)
+                            .width(iced::Length::Fill),
+                        button(tr("复制错误")).on_press(Message::CopyText(message)),
+                    ]
+                    .spacing(10)
+                    .align_y(iced::Alignment::Center),
+                );
+            }
+            if let Some(metadata) = &report.metadata {
+                log = log.push(text(trf(
+                    "{} -> {}: 元数据 {}",
+                    &[&file_name, &format, metadata],
+                )));
+            }
+            for note in report.notes.iter() {
+                log =
+                    log.push(text(format!("{file_name} -> {format}: {note}")).style(text::warning));
+            }
+        }
+        let failed = self
+            .reports
+            .iter()
+            .filter(|report| report.error.is_some())
+            .count();
+        let mut log_panel = column![
+            row![
+                button(if self.show_log {
+                    tr("收起日志")
+                } else {
+                    tr("展开日志")
+                })
+                .on_press(Message::ToggleLog)
+                .style(button::secondary),
+                text(trf(
+                    "{} 个成功, {} 个失败",
+                    &[&(self.reports.len() - failed), &failed]
+                )),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+        ]
+        .spacing(10);
+        if self.show_log {
+            log_panel = log_panel.push(scrollable(log).width(iced::Length::Fill).height(150));
         }
 
         let show_iamges_list = container(
@@ -267,11 +1508,13 @@ impl App {
                     select_files_button,
                     select_folders_button,
                     clear_button,
-                    convert_button
+                    convert_button,
+                    merge_tiff_button
                 ]
                 .width(iced::Length::Fill)
                 .height(30)
                 .spacing(10),
+                list_controls,
                 container(
                     scrollable(images_list)
                         .width(iced::Length::Fill)
@@ -281,6 +1524,7 @@ impl App {
                 .height(iced::Length::Fill)
                 .padding(10)
                 .style(container::bordered_box),
+                log_panel,
             ]
             .spacing(10),
         )
@@ -289,39 +1533,552 @@ impl App {
         .padding(10)
         .style(container::rounded_box);
 
+        let svg_options = &self.convert_options.svg;
+        let mut svg_settings = column![
+            text(tr("SVG 渲染")),
+            pick_list(
+                SvgSizeKind::ALL,
+                Some(svg_options.size.kind()),
+                Message::SvgSizeKindSelected,
+            )
+            .width(iced::Length::Fill),
+        ]
+        .spacing(10);
+        if svg_options.size.kind() != SvgSizeKind::Original {
+            svg_settings = svg_settings.push(
+                text_input("256x256", &self.svg_size_input).on_input(Message::SvgSizeChanged),
+            );
+        }
+        svg_settings = svg_settings.push(
+            pick_list(SvgFit::ALL, Some(svg_options.fit), Message::SvgFitSelected)
+                .width(iced::Length::Fill),
+        );
+        svg_settings = svg_settings.push(
+            text_input(tr("多尺寸: 24, 48 / 1x, 2x"), &self.svg_sizes_input)
+                .on_input(Message::SvgSizesChanged),
+        );
+        svg_settings = svg_settings.push(
+            row![
+                text(tr("留白")),
+                number_input("0", svg_options.padding, Message::SvgPaddingChanged),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+        );
+        svg_settings = svg_settings.push(
+            row![
+                text(tr("背景")),
+                text_input("#RRGGBBAA", &self.svg_background_input)
+                    .on_input(Message::SvgBackgroundChanged),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+        );
+        svg_settings = svg_settings.push(
+            text_input(tr("默认字体"), &svg_options.default_font_family)
+                .on_input(Message::SvgDefaultFontChanged),
+        );
+        for (index, dir) in svg_options.font_dirs.iter().enumerate() {
+            svg_settings = svg_settings.push(
+                row![
+                    text(
+                        dir.file_name()
+                            .and_then(OsStr::to_str)
+                            .unwrap_or(tr("<未知文件夹>"))
+                            .to_owned()
+                    )
+                    .width(iced::Length::Fill),
+                    button("x").on_press(Message::RemoveSvgFontDir(index)),
+                ]
+                .spacing(10)
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        svg_settings = svg_settings.push(
+            button(tr("添加字体文件夹"))
+                .on_press(Message::AddSvgFontDir)
+                .width(iced::Length::Fill),
+        );
+        svg_settings = svg_settings.push(
+            checkbox(tr("阻止外部资源"), svg_options.block_external)
+                .on_toggle(Message::SvgBlockExternalToggled),
+        );
+
+        let optimize = svg_options.optimize;
+        let mut optimize_settings = column![
+            text(tr("SVG 精简")),
+            checkbox(tr("精简 SVG 输出"), optimize.enabled).on_toggle(move |enabled| {
+                Message::SvgOptimizeChanged(SvgOptimize {
+                    enabled,
+                    ..optimize
+                })
+            }),
+        ]
+        .spacing(10);
+        if optimize.enabled {
+            optimize_settings = optimize_settings.extend([
+                labeled_slider(
+                    tr("坐标精度"),
+                    optimize.precision.to_string(),
+                    slider(0..=6, optimize.precision, move |precision| {
+                        Message::SvgOptimizeChanged(SvgOptimize {
+                            precision,
+                            ..optimize
+                        })
+                    }),
+                )
+                .into(),
+                checkbox(tr("合并路径"), optimize.merge_paths)
+                    .on_toggle(move |merge_paths| {
+                        Message::SvgOptimizeChanged(SvgOptimize {
+                            merge_paths,
+                            ..optimize
+                        })
+                    })
+                    .into(),
+                checkbox(tr("压缩空白"), optimize.minify)
+                    .on_toggle(move |minify| {
+                        Message::SvgOptimizeChanged(SvgOptimize { minify, ..optimize })
+                    })
+                    .into(),
+            ]);
+        }
+
+        let trace = self.convert_options.trace;
+        let trace_settings = column![
+            text(tr("矢量化 (SVG)")),
+            pick_list(
+                TracePreset::ALL,
+                Some(trace.preset),
+                Message::TracePresetSelected
+            )
+            .width(iced::Length::Fill),
+            checkbox(tr("彩色"), trace.color)
+                .on_toggle(move |color| { Message::TraceChanged(TraceOptions { color, ..trace }) }),
+            checkbox(tr("堆叠"), trace.stacked).on_toggle(move |stacked| {
+                Message::TraceChanged(TraceOptions { stacked, ..trace })
+            }),
+            pick_list(TraceCurve::ALL, Some(trace.curve), move |curve| {
+                Message::TraceChanged(TraceOptions { curve, ..trace })
+            })
+            .width(iced::Length::Fill),
+            labeled_slider(
+                tr("过滤斑点"),
+                trace.filter_speckle.to_string(),
+                slider(0..=128, trace.filter_speckle, move |filter_speckle| {
+                    Message::TraceChanged(TraceOptions {
+                        filter_speckle,
+                        ..trace
+                    })
+                }),
+            ),
+            labeled_slider(
+                tr("颜色精度"),
+                trace.color_precision.to_string(),
+                slider(1..=8, trace.color_precision, move |color_precision| {
+                    Message::TraceChanged(TraceOptions {
+                        color_precision,
+                        ..trace
+                    })
+                }),
+            ),
+            labeled_slider(
+                tr("层差"),
+                trace.layer_difference.to_string(),
+                slider(0..=255, trace.layer_difference, move |layer_difference| {
+                    Message::TraceChanged(TraceOptions {
+                        layer_difference,
+                        ..trace
+                    })
+                }),
+            ),
+            labeled_slider(
+                tr("角度阈值"),
+                trace.corner_threshold.to_string(),
+                slider(0..=180, trace.corner_threshold, move |corner_threshold| {
+                    Message::TraceChanged(TraceOptions {
+                        corner_threshold,
+                        ..trace
+                    })
+                }),
+            ),
+            labeled_slider(
+                tr("长度阈值"),
+                trace.length_threshold.to_string(),
+                slider(
+                    3.5..=10.0,
+                    trace.length_threshold,
+                    move |length_threshold| {
+                        Message::TraceChanged(TraceOptions {
+                            length_threshold,
+                            ..trace
+                        })
+                    }
+                )
+                .step(0.5),
+            ),
+            labeled_slider(
+                tr("拼接阈值"),
+                trace.splice_threshold.to_string(),
+                slider(0..=180, trace.splice_threshold, move |splice_threshold| {
+                    Message::TraceChanged(TraceOptions {
+                        splice_threshold,
+                        ..trace
+                    })
+                }),
+            ),
+        ]
+        .spacing(10);
+
+        let resize = self.convert_options.resize;
+        let mut resize_settings = column![
+            text(tr("缩放")),
+            pick_list(ResizeKind::ALL, Some(resize.kind), move |kind| {
+                Message::ResizeChanged(ResizeOptions { kind, ..resize })
+            })
+            .width(iced::Length::Fill),
+        ]
+        .spacing(10);
+        match resize.kind {
+            ResizeKind::None => {}
+            ResizeKind::MaxSize | ResizeKind::Exact => {
+                resize_settings = resize_settings.push(
+                    row![
+                        number_input(tr("宽"), resize.width, move |width| {
+                            Message::ResizeChanged(ResizeOptions { width, ..resize })
+                        }),
+                        text("x"),
+                        number_input(tr("高"), resize.height, move |height| {
+                            Message::ResizeChanged(ResizeOptions { height, ..resize })
+                        }),
+                    ]
+                    .spacing(5)
+                    .align_y(iced::Alignment::Center),
+                );
+            }
+            ResizeKind::Percent => {
+                resize_settings = resize_settings.push(
+                    row![
+                        number_input("50", resize.percent, move |percent| {
+                            Message::ResizeChanged(ResizeOptions { percent, ..resize })
+                        }),
+                        text("%"),
+                    ]
+                    .spacing(5)
+                    .align_y(iced::Alignment::Center),
+                );
+            }
+            ResizeKind::LongEdge | ResizeKind::ShortEdge => {
+                resize_settings =
+                    resize_settings.push(number_input("1920", resize.edge, move |edge| {
+                        Message::ResizeChanged(ResizeOptions { edge, ..resize })
+                    }));
+            }
+        }
+        if resize.kind == ResizeKind::Exact {
+            resize_settings = resize_settings.push(
+                pick_list(ResizeFit::ALL, Some(resize.fit), move |fit| {
+                    Message::ResizeChanged(ResizeOptions { fit, ..resize })
+                })
+                .width(iced::Length::Fill),
+            );
+            if resize.fit == ResizeFit::Cover {
+                resize_settings = resize_settings.push(
+                    pick_list(CropAnchor::ALL, Some(resize.anchor), move |anchor| {
+                        Message::ResizeChanged(ResizeOptions { anchor, ..resize })
+                    })
+                    .width(iced::Length::Fill),
+                );
+            }
+        }
+        if resize.kind != ResizeKind::None {
+            resize_settings = resize_settings.extend([
+                checkbox(tr("不放大"), resize.no_upscale)
+                    .on_toggle(move |no_upscale| {
+                        Message::ResizeChanged(ResizeOptions {
+                            no_upscale,
+                            ..resize
+                        })
+                    })
+                    .into(),
+                pick_list(ResizeFilter::ALL, Some(resize.filter), move |filter| {
+                    Message::ResizeChanged(ResizeOptions { filter, ..resize })
+                })
+                .width(iced::Length::Fill)
+                .into(),
+            ]);
+        }
+
+        let compress = self.convert_options.compress;
+        let matte = self.convert_options.matte;
+        let mut raster_settings = column![
+            text(tr("EXIF 方向")),
+            pick_list(
+                OrientationMode::ALL,
+                Some(self.convert_options.orientation),
+                Message::OrientationSelected
+            )
+            .width(iced::Length::Fill),
+            text(tr("元数据")),
+            pick_list(
+                MetadataPolicy::ALL,
+                Some(self.convert_options.metadata),
+                Message::MetadataPolicySelected
+            )
+            .width(iced::Length::Fill),
+            checkbox(tr("压缩 PNG"), compress.enabled).on_toggle(move |enabled| {
+                Message::CompressChanged(CompressOptions {
+                    enabled,
+                    ..compress
+                })
+            }),
+        ]
+        .spacing(10);
+        raster_settings = raster_settings.extend([
+            checkbox(tr("保留文件时间"), self.convert_options.preserve_timestamps)
+                .on_toggle(Message::PreserveTimestampsToggled)
+                .into(),
+            checkbox(tr("降低位深时抖动"), self.convert_options.dither)
+                .on_toggle(Message::DitherToggled)
+                .into(),
+            text(tr("高位深图片保存为 AVIF 时按 10 位写入")).into(),
+            text(tr("透明底色 (JPEG/BMP/应用图标)")).into(),
+            text_input("#RRGGBB", &self.matte_color_input)
+                .on_input(Message::MatteColorChanged)
+                .into(),
+            checkbox(tr("线性混合边缘"), matte.premultiplied_edges)
+                .on_toggle(move |premultiplied_edges| {
+                    Message::MatteChanged(MatteOptions {
+                        premultiplied_edges,
+                        ..matte
+                    })
+                })
+                .into(),
+            text(tr("TIFF 压缩")).into(),
+            pick_list(
+                TiffCompression::ALL,
+                Some(self.convert_options.tiff),
+                Message::TiffCompressionSelected,
+            )
+            .width(iced::Length::Fill)
+            .into(),
+            text(tr("颜色管理")).into(),
+            pick_list(
+                ColorTarget::ALL,
+                Some(self.convert_options.color.target),
+                Message::ColorTargetSelected,
+            )
+            .width(iced::Length::Fill)
+            .into(),
+        ]);
+        if self.convert_options.color.target == ColorTarget::Profile {
+            let profile_name = self
+                .convert_options
+                .color
+                .profile_path
+                .as_deref()
+                .and_then(Path::file_name)
+                .and_then(OsStr::to_str)
+                .unwrap_or(tr("未选择配置文件"));
+            raster_settings = raster_settings.extend([
+                text(profile_name).into(),
+                button(tr("选择 ICC 文件"))
+                    .on_press(Message::SelectIccProfile)
+                    .width(iced::Length::Fill)
+                    .into(),
+            ]);
+        }
+        raster_settings = raster_settings.push(labeled_slider(
+            tr("质量 (JPEG/AVIF)"),
+            self.convert_options.quality.to_string(),
+            slider(
+                1..=100,
+                self.convert_options.quality,
+                Message::QualityChanged,
+            ),
+        ));
+        if compress.enabled {
+            raster_settings = raster_settings.push(labeled_slider(
+                tr("压缩等级"),
+                compress.level.to_string(),
+                slider(0..=6, compress.level, move |level| {
+                    Message::CompressChanged(CompressOptions { level, ..compress })
+                }),
+            ));
+        }
+
+        let mut output_dir_row = row![
+            text(
+                self.convert_options
+                    .output_dir
+                    .as_ref()
+                    .map_or(tr("与原文件相同").to_string(), |dir| dir
+                        .display()
+                        .to_string())
+            )
+            .width(iced::Length::Fill)
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center);
+        if self.convert_options.output_dir.is_some() {
+            output_dir_row = output_dir_row.push(button("x").on_press(Message::ClearOutputDir));
+        }
+        let output_settings = column![
+            text(tr("输出")),
+            output_dir_row,
+            button(tr("选择输出文件夹"))
+                .on_press(Message::SelectOutputDir)
+                .width(iced::Length::Fill),
+            row![
+                text(tr("文件名")),
+                text_input("{name}", &self.convert_options.name_template)
+                    .on_input(Message::NameTemplateChanged),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+        ]
+        .spacing(10);
+
+        let ui_font = match &self.config.ui_font {
+            Some(family) => UiFont::Family(family.clone()),
+            None => UiFont::Auto,
+        };
+        let ui_settings = column![
+            text(tr("语言")),
+            pick_list(
+                Language::ALL,
+                Some(i18n::language()),
+                Message::LanguageSelected
+            )
+            .width(iced::Length::Fill),
+            text(tr("界面字体")),
+            pick_list(
+                self.font_families.as_slice(),
+                Some(ui_font),
+                Message::UiFontSelected
+            )
+            .width(iced::Length::Fill),
+            text(tr("重启后生效")),
+        ]
+        .spacing(10);
+        let ui_settings = if self.config.ui_font.is_none() && !self.has_cjk_font {
+            ui_settings.push(
+                text(tr("没有找到中文字体, 中文可能显示为方框, 请选择其他字体"))
+                    .style(text::warning),
+            )
+        } else {
+            ui_settings
+        };
+
+        let presets = preset::all(&self.config.presets);
+        let active_preset = self
+            .active_preset
+            .as_ref()
+            .and_then(|id| presets.iter().find(|preset| preset.id == *id))
+            .cloned();
+        let is_saved_preset = self
+            .active_preset
+            .as_ref()
+            .is_some_and(|id| self.config.presets.iter().any(|p| p.id == *id));
+        let preset_settings = column![
+            text(tr("预设")),
+            pick_list(presets, active_preset, Message::PresetSelected)
+                .placeholder(tr("选择预设"))
+                .width(iced::Length::Fill),
+            text_input(tr("预设名称"), &self.preset_name_input)
+                .on_input(Message::PresetNameChanged),
+            row![
+                button(tr("保存"))
+                    .on_press_maybe(
+                        (!self.preset_name_input.trim().is_empty()).then_some(Message::SavePreset)
+                    )
+                    .width(iced::Length::Fill),
+                button(tr("删除"))
+                    .on_press_maybe(is_saved_preset.then_some(Message::DeletePreset))
+                    .width(iced::Length::Fill),
+            ]
+            .spacing(10),
+        ]
+        .spacing(10);
+
         let show_image_format = container(
             scrollable(
-                Column::with_children(self.convert_img_format.iter().map(
-                    |(image_formamt, should_convert)| {
-                        checkbox(image_formamt.get_name(), *should_convert)
-                            .on_toggle(|_| {
-                                Message::ToggleImageFormatItem(*image_formamt, *should_convert)
-                            })
+                column![
+                    preset_settings,
+                    Column::with_children(self.format_order.iter().enumerate().map(
+                        |(index, image_formamt)| {
+                            let should_convert =
+                                self.convert_img_format.get(image_formamt) == Some(&true);
+                            row![
+                                checkbox(image_formamt.get_name(), should_convert)
+                                    .on_toggle(move |_| {
+                                        Message::ToggleImageFormatItem(
+                                            *image_formamt,
+                                            should_convert,
+                                        )
+                                    })
+                                    .width(iced::Length::Fill),
+                                button("↑").on_press_maybe(
+                                    (index > 0)
+                                        .then_some(Message::MoveFormat(*image_formamt, true))
+                                ),
+                                button("↓").on_press_maybe(
+                                    (index + 1 < self.format_order.len())
+                                        .then_some(Message::MoveFormat(*image_formamt, false))
+                                ),
+                            ]
+                            .spacing(5)
+                            .align_y(iced::Alignment::Center)
                             .into()
-                    },
-                ))
-                .spacing(10),
+                        },
+                    ))
+                    .spacing(10),
+                    output_settings,
+                    resize_settings,
+                    raster_settings,
+                    svg_settings,
+                    optimize_settings,
+                    trace_settings,
+                    ui_settings,
+                ]
+                .spacing(20),
             )
             .width(iced::Length::Fill)
             .height(iced::Length::Fill),
         )
-        .width(100)
+        .width(200)
         .height(iced::Length::Fill)
         .padding(10)
         .style(container::bordered_box);
 
-        let interface = row![show_iamges_list, show_image_format,]
-            .spacing(10)
-            .padding(10);
+        let main_panel = match &self.preview {
+            Some(Ok(preview)) => self.preview_view(preview),
+            _ => show_iamges_list.into(),
+        };
+        let mut interface = row![main_panel].spacing(10).padding(10);
+        if let Some(path) = &self.selected_image {
+            interface = interface.push(self.details_view(path));
+        }
+        interface = interface.push(show_image_format);
 
         interface.into()
     }
 
     fn subscription(&self) -> Subscription<Message> {
+        use iced::Event::Keyboard;
         use iced::Event::Window;
-        use iced::window::Event::FileDropped;
-        iced::event::listen_with(|event, _, _| match event {
+        use iced::event::Status;
+        use iced::keyboard::Event::{KeyPressed, ModifiersChanged};
+        use iced::window::Event::{CloseRequested, FileDropped, Resized};
+        iced::event::listen_with(|event, status, _| match event {
             Window(FileDropped(path)) => Some(Message::DropFile(path)),
+            Window(Resized(size)) => Some(Message::WindowResized(size)),
+            Window(CloseRequested) => Some(Message::CloseRequested),
+            Keyboard(ModifiersChanged(modifiers)) => Some(Message::ModifiersChanged(modifiers)),
+            // 输入框获得焦点时按键由输入框处理
+            Keyboard(KeyPressed { key, modifiers, .. }) if status == Status::Ignored => {
+                Some(Message::KeyPressed(key, modifiers))
+            }
             _ => None,
         })
     }
@@ -330,13 +2087,443 @@ impl App {
         if let Some(mime) = tika_magic::from_filepath(file_path) {
             if let Some(format) = ImageFormatExt::get_format_from_mime(mime) {
                 self.images.insert(file_path.into(), (format, false));
+                if let Ok(metadata) = std::fs::metadata(file_path) {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    self.file_stats
+                        .insert(file_path.into(), (metadata.len(), modified));
+                }
             } else {
                 println!("Not an image or image does not support conversion: \n{file_path:?}\n")
             }
         }
     }
 
+    /// 替换全部转换设置, 并同步依赖设置的输入框
+    fn set_convert_options(&mut self, options: ConvertOptions) {
+        self.svg_size_input = options.svg.size.to_string();
+        self.svg_sizes_input = SvgSize::format_list(&options.svg.sizes);
+        self.svg_background_input = options
+            .svg
+            .background
+            .map(|[r, g, b, a]| format!("#{r:02X}{g:02X}{b:02X}{a:02X}"))
+            .unwrap_or_default();
+        let [r, g, b] = options.matte.color;
+        self.matte_color_input = format!("#{r:02X}{g:02X}{b:02X}");
+        self.convert_options = options;
+    }
+
+    fn save_config(&mut self) {
+        self.config.formats = ImageFormatExt::ALL
+            .into_iter()
+            .filter(|format| self.convert_img_format.get(format) == Some(&true))
+            .collect();
+        self.config.format_order = self.format_order.clone();
+        self.config.convert = self.convert_options.clone();
+        // 还没选择是否恢复时保留上次的列表
+        self.config.last_session = if self.images.is_empty() {
+            self.restore_session.clone()
+        } else {
+            let mut paths = self.images.keys().cloned().collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+        if let Err(e) = self.config.save() {
+            println!("Failed to save config\n{e:?}");
+        }
+    }
+
+    /// 从列表中移除文件, 同时清理缓存和相关的面板
+    fn remove_images(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.images.remove(path);
+            self.thumbnails.remove(path);
+            self.dimensions.remove(path);
+            self.file_stats.remove(path);
+            self.details.remove(path);
+            self.statuses.remove(path);
+        }
+        let mut pending = self
+            .pending_thumbnails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        paths.iter().for_each(|path| {
+            pending.remove(path);
+        });
+        drop(pending);
+        self.refresh_visible();
+        if self
+            .selected_image
+            .as_ref()
+            .is_some_and(|path| paths.contains(path))
+        {
+            self.selected_image = None;
+        }
+        if self
+            .selection_anchor
+            .as_ref()
+            .is_some_and(|path| paths.contains(path))
+        {
+            self.selection_anchor = None;
+        }
+        if let Some(Ok(preview)) = &self.preview
+            && paths.contains(&preview.input_path)
+        {
+            self.preview = None;
+            self.preview_handle = None;
+        }
+    }
+
+    /// 经过筛选并排序后显示在列表中的文件
+    fn visible_images(&self) -> &[PathBuf] {
+        &self.visible
+    }
+
+    /// 已勾选但被筛选隐藏的文件数, 转换时同样会处理
+    fn hidden_checked(&self) -> usize {
+        let checked = |path: &PathBuf| self.images.get(path).is_some_and(|(_, c)| *c);
+        let visible = self.visible.iter().filter(|path| checked(path)).count();
+        self.images.keys().filter(|path| checked(path)).count() - visible
+    }
+
+    /// 已勾选的文件, 先按列表中显示的顺序, 再接上被筛选隐藏的 (按路径)
+    fn checked_in_order(&self) -> Vec<(PathBuf, ImageFormatExt)> {
+        let checked = |path: &PathBuf| {
+            self.images
+                .get(path)
+                .and_then(|(format, checked)| checked.then_some((path.clone(), *format)))
+        };
+        let visible = self.visible.iter().collect::<HashSet<_>>();
+        let mut hidden = self
+            .images
+            .keys()
+            .filter(|path| !visible.contains(path))
+            .filter_map(checked)
+            .collect::<Vec<_>>();
+        hidden.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        self.visible
+            .iter()
+            .filter_map(checked)
+            .chain(hidden)
+            .collect()
+    }
+
+    fn refresh_visible(&mut self) {
+        let filter = self.filter_input.trim().to_lowercase();
+        let mut paths = self
+            .images
+            .iter()
+            .filter(|(path, (format, _))| {
+                self.format_filter.is_none_or(|f| f == *format)
+                    && (filter.is_empty()
+                        || path.to_string_lossy().to_lowercase().contains(&filter))
+            })
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+
+        let file_name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .unwrap_or_default()
+        };
+        paths.sort_by(|a, b| {
+            let ordering = match self.sort_key {
+                SortKey::Name => file_name(a).cmp(&file_name(b)),
+                SortKey::Path => std::cmp::Ordering::Equal,
+                SortKey::Size => {
+                    let size = |path: &Path| self.file_stats.get(path).map(|(size, _)| *size);
+                    size(a).cmp(&size(b))
+                }
+                SortKey::Format => {
+                    let format = |path: &Path| self.images.get(path).map(|(f, _)| f.get_name());
+                    format(a).cmp(&format(b))
+                }
+                SortKey::Dimensions => {
+                    let area = |path: &Path| {
+                        self.dimensions
+                            .get(path)
+                            .map(|(width, height)| *width as u64 * *height as u64)
+                    };
+                    area(a).cmp(&area(b))
+                }
+                SortKey::Modified => {
+                    let modified = |path: &Path| self.file_stats.get(path).map(|(_, time)| *time);
+                    modified(a).cmp(&modified(b))
+                }
+            }
+            // 其余相同时按路径排序, 保证顺序稳定
+            .then_with(|| a.cmp(b));
+            if self.sort_descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        self.visible = paths;
+    }
+
+    /// 按当前勾选状态重新设置可见文件的勾选
+    fn check_visible(&mut self, checked: impl Fn(&ImageFormatExt, bool) -> bool) {
+        for path in self.visible_images().to_vec() {
+            if let Some((format, is_checked)) = self.images.get_mut(&path) {
+                *is_checked = checked(format, *is_checked);
+            }
+        }
+    }
+
+    fn update_preview_handle(&mut self) {
+        self.preview_handle = match &self.preview {
+            Some(Ok(preview)) => {
+                let image = match &preview.encoded {
+                    Some(encoded) => {
+                        compare::split_view(&preview.source, encoded, self.preview_split)
+                    }
+                    None => preview.source.clone(),
+                };
+                Some(Handle::from_rgba(
+                    image.width(),
+                    image.height(),
+                    image.into_raw(),
+                ))
+            }
+            _ => None,
+        };
+    }
+
+    fn preview_view<'a>(&'a self, preview: &'a Preview) -> Element<'a, Message> {
+        let file_name = preview
+            .input_path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or(tr("<未知文件名>"));
+        let format = preview.convert_format.get_name();
+        let mut preview_column = column![
+            row![
+                text(trf("{}: 左侧原图, 右侧 {}", &[&file_name, &format]))
+                    .width(iced::Length::Fill),
+                button(tr("返回列表")).on_press(Message::ClosePreview)
+            ]
+            .spacing(10)
+        ]
+        .spacing(10);
+
+        if let Some(handle) = &self.preview_handle {
+            preview_column = preview_column.push(
+                iced::widget::image(handle.clone())
+                    .width(iced::Length::Fill)
+                    .height(iced::Length::Fill),
+            );
+        }
+        if preview.encoded.is_some() {
+            preview_column = preview_column.push(
+                slider(0.0..=1.0, self.preview_split, Message::PreviewSplitChanged).step(0.01),
+            );
+        }
+
+        let ratio = preview.encoded_size as f64 / preview.source_size.max(1) as f64 * 100.0;
+        preview_column = preview_column.push(text(trf(
+            "大小: {} -> {} ({}%)",
+            &[
+                &details::format_size(preview.source_size),
+                &details::format_size(preview.encoded_size),
+                &format!("{ratio:.1}"),
+            ],
+        )));
+        if let (Some(psnr), Some(ssim)) = (preview.psnr, preview.ssim) {
+            let psnr = if psnr.is_infinite() {
+                tr("无损").to_string()
+            } else {
+                format!("{psnr:.2} dB")
+            };
+            preview_column = preview_column.push(text(format!("PSNR: {psnr}    SSIM: {ssim:.4}")));
+        }
+        for note in preview.notes.iter() {
+            preview_column = preview_column.push(text(note.clone()).style(text::warning));
+        }
+
+        container(preview_column)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .padding(10)
+            .style(container::rounded_box)
+            .into()
+    }
+
+    fn details_view<'a>(&'a self, path: &'a Path) -> Element<'a, Message> {
+        let file_name = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or(tr("<未知文件名>"));
+        let mut details_column = column![
+            row![
+                text(file_name).width(iced::Length::Fill),
+                button(tr("关闭")).on_press(Message::CloseDetails)
+            ]
+            .spacing(10)
+        ]
+        .spacing(10);
+
+        match self.details.get(path) {
+            None => details_column = details_column.push(text(tr("读取中..."))),
+            Some(Err(error)) => {
+                details_column = details_column.push(text(error.clone()).style(text::danger))
+            }
+            Some(Ok(details)) => {
+                let format = self
+                    .images
+                    .get(path)
+                    .map_or("", |(format, _)| format.get_name());
+                let mut fields = vec![
+                    (tr("格式"), format.to_string()),
+                    (tr("文件大小"), details::format_size(details.file_size)),
+                    (
+                        "MIME",
+                        details.mime.clone().unwrap_or(tr("未知").to_string()),
+                    ),
+                ];
+                if let Some((width, height)) = details.dimensions {
+                    fields.push((tr("尺寸"), format!("{width} x {height}")));
+                }
+                if let Some(color) = &details.color {
+                    fields.push((tr("颜色类型"), color.clone()));
+                }
+                if let Some(bit_depth) = details.bit_depth {
+                    fields.push((tr("位深"), trf("{} 位", &[&bit_depth])));
+                }
+                if let Some(view_box) = &details.svg_view_box {
+                    fields.push(("viewBox", view_box.clone()));
+                }
+                if let Some(frames) = details.frames {
+                    fields.push((tr("动画帧数"), frames.to_string()));
+                }
+                if let Some(pages) = details.pages {
+                    fields.push((tr("页数"), pages.to_string()));
+                }
+                for (label, value) in fields {
+                    details_column = details_column.push(labeled_value(label, value));
+                }
+
+                if details.extension_mismatch {
+                    details_column = details_column
+                        .push(text(tr("扩展名与文件内容不一致")).style(text::warning));
+                }
+                if !details.ico_entries.is_empty() {
+                    details_column = details_column.push(text(tr("ICO 图标")));
+                    for (width, height, bits_per_pixel) in details.ico_entries.iter() {
+                        details_column = details_column.push(text(trf(
+                            "{} x {}, {} 位",
+                            &[width, height, bits_per_pixel],
+                        )));
+                    }
+                }
+                if !details.exif.is_empty() {
+                    details_column = details_column.push(text("EXIF"));
+                    for (label, value) in details.exif.iter() {
+                        details_column = details_column.push(labeled_value(label, value.clone()));
+                    }
+                }
+            }
+        }
+
+        let preview_formats = ImageFormatExt::ALL
+            .into_iter()
+            .filter(|format| format.get_format().is_some())
+            .collect::<Vec<_>>();
+        details_column = details_column.extend([
+            text(tr("对比预览")).into(),
+            pick_list(
+                preview_formats,
+                Some(self.preview_format),
+                Message::PreviewFormatSelected,
+            )
+            .width(iced::Length::Fill)
+            .into(),
+            button(if self.previewing {
+                tr("生成中...")
+            } else {
+                tr("生成预览")
+            })
+            .on_press_maybe((!self.previewing).then_some(Message::PreviewImage))
+            .width(iced::Length::Fill)
+            .into(),
+        ]);
+        if let Some(Err(error)) = &self.preview {
+            details_column = details_column.push(text(error.clone()).style(text::danger));
+        }
+
+        container(scrollable(details_column).height(iced::Length::Fill))
+            .width(240)
+            .height(iced::Length::Fill)
+            .padding(10)
+            .style(container::bordered_box)
+            .into()
+    }
+
+    /// 在后台解码还没有缩略图的文件, 先处理列表中可见的文件
+    fn load_thumbnails(&mut self) -> Task<Message> {
+        let visible = self.visible.iter().collect::<HashSet<_>>();
+        let mut pending = self
+            .visible
+            .iter()
+            .chain(self.images.keys().filter(|path| !visible.contains(path)))
+            .filter(|path| !self.thumbnails.contains_key(*path))
+            .map(|path| (path.clone(), self.images[path].0))
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return Task::none();
+        }
+        pending.iter().for_each(|(path, _)| {
+            self.thumbnails.insert(path.clone(), None);
+        });
+        self.pending_thumbnails
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(pending.iter().map(|(path, _)| path.clone()));
+
+        let (sender, receiver) = iced::futures::channel::mpsc::unbounded();
+        let wanted = self.pending_thumbnails.clone();
+        pending.reverse();
+        let queue = Arc::new(Mutex::new(pending));
+        for _ in 0..THUMBNAIL_THREADS {
+            let (queue, wanted, sender) = (queue.clone(), wanted.clone(), sender.clone());
+            std::thread::spawn(move || {
+                loop {
+                    let Some((path, format)) =
+                        queue.lock().unwrap_or_else(|e| e.into_inner()).pop()
+                    else {
+                        break;
+                    };
+                    // 已从列表移除的文件不再解码
+                    if !wanted
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(&path)
+                    {
+                        continue;
+                    }
+                    let thumbnail = convert::thumbnail(&path, &format, THUMBNAIL_SIZE)
+                        .inspect_err(|e| println!("Failed to load thumbnail {path:?}: {e:?}"))
+                        .ok()
+                        .map(|(image, dimensions)| {
+                            let handle =
+                                Handle::from_rgba(image.width(), image.height(), image.into_raw());
+                            (handle, dimensions)
+                        });
+                    if sender.unbounded_send((path, thumbnail)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        Task::run(receiver, |(path, thumbnail)| {
+            Message::ThumbnailLoaded(path, thumbnail)
+        })
+    }
+
     fn get_image_file_from_folder(&mut self, folder_path: &Path) {
+        self.config.add_recent_folder(folder_path.to_path_buf());
         WalkDir::new(folder_path)
             .into_iter()
             .filter_map(|e| e.ok().filter(|e| e.file_type().is_file()))
@@ -345,3 +2532,52 @@ impl App {
             });
     }
 }
+
+/// 用系统文件管理器打开文件夹
+fn open_folder(path: &Path) {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    if let Err(e) = std::process::Command::new(program).arg(path).spawn() {
+        println!("Failed to open folder {path:?}: {e:?}");
+    }
+}
+
+fn labeled_slider<'a>(
+    label: &'a str,
+    value: String,
+    slider: impl Into<Element<'a, Message>>,
+) -> Column<'a, Message> {
+    column![
+        row![text(label).width(iced::Length::Fill), text(value)],
+        slider.into()
+    ]
+}
+
+fn labeled_value<'a>(label: &'a str, value: String) -> iced::widget::Row<'a, Message> {
+    row![text(label).width(80), text(value)].spacing(10)
+}
+
+/// 只接受非负整数的输入框, 清空时为 0
+fn number_input<'a>(
+    placeholder: &'a str,
+    value: u32,
+    on_input: impl Fn(u32) -> Message + 'a,
+) -> iced::widget::TextInput<'a, Message> {
+    let text = if value == 0 {
+        String::new()
+    } else {
+        value.to_string()
+    };
+    text_input(placeholder, &text).on_input(move |input| {
+        if input.is_empty() {
+            on_input(0)
+        } else {
+            on_input(input.parse().unwrap_or(value))
+        }
+    })
+}
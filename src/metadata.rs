@@ -0,0 +1,789 @@
+use std::{
+    fmt,
+    io::{BufWriter, Read},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use image::codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder};
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageFormat, metadata::Orientation};
+use serde::{Deserialize, Serialize};
+
+use crate::depth;
+use crate::i18n::{tr, trf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OrientationMode {
+    /// 按 EXIF 方向旋转像素, 输出不再带方向标记
+    #[default]
+    Apply,
+    /// 像素保持原样, 把方向标记写进输出 (仅 JPEG/PNG/WEBP, 其他格式按 Apply 处理)
+    Preserve,
+}
+
+impl OrientationMode {
+    pub const ALL: [OrientationMode; 2] = [OrientationMode::Apply, OrientationMode::Preserve];
+}
+
+impl fmt::Display for OrientationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            OrientationMode::Apply => "按 EXIF 旋转",
+            OrientationMode::Preserve => "保留方向标记",
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MetadataPolicy {
+    KeepAll,
+    /// 只留 ICC 配置文件和 EXIF 中的版权信息
+    #[default]
+    IccAndCopyright,
+    /// 全部清除, 保留方向标记时仍会写入方向
+    StripAll,
+}
+
+impl MetadataPolicy {
+    pub const ALL: [MetadataPolicy; 3] = [
+        MetadataPolicy::KeepAll,
+        MetadataPolicy::IccAndCopyright,
+        MetadataPolicy::StripAll,
+    ];
+}
+
+impl fmt::Display for MetadataPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            MetadataPolicy::KeepAll => "全部保留",
+            MetadataPolicy::IccAndCopyright => "仅 ICC 和版权",
+            MetadataPolicy::StripAll => "全部清除",
+        }))
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp\0";
+
+const EXIF_FIELDS: [(u16, &str); 12] = [
+    (0x010F, "相机厂商"),
+    (0x0110, "相机型号"),
+    (0x0112, "方向"),
+    (0x9003, "拍摄时间"),
+    (0x0132, "修改时间"),
+    (0x829A, "曝光时间"),
+    (0x829D, "光圈"),
+    (0x8827, "ISO"),
+    (0x920A, "焦距"),
+    (0x0131, "软件"),
+    (0x013B, "作者"),
+    (0x8298, "版权"),
+];
+
+/// 能写入 ICC/EXIF/XMP 的输出格式
+pub fn supports_metadata(format: ImageFormat) -> bool {
+    matches!(
+        format,
+        ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP
+    )
+}
+
+/// 输入文件中的元数据, EXIF 为 TIFF 结构 (不含 "Exif\0\0" 前缀)
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub icc: Option<Vec<u8>>,
+    pub exif: Option<Vec<u8>>,
+    pub xmp: Option<Vec<u8>>,
+}
+
+impl Metadata {
+    pub fn read(decoder: &mut impl ImageDecoder, input_path: &Path) -> Result<Self> {
+        let data = std::fs::read(input_path)
+            .with_context(|| format!("Failed to read file '{input_path:?}'"))?;
+        // image 的 PNG 解码器不读 eXIf, 自己从块里取
+        let exif = match decoder.exif_metadata()? {
+            Some(exif) => Some(exif),
+            None => read_png_exif(&data),
+        };
+
+        Ok(Metadata {
+            icc: decoder.icc_profile()?,
+            exif,
+            xmp: read_xmp(&data),
+        })
+    }
+
+    /// 按策略筛选, `orientation` 是输出中应记录的方向 (像素已旋转时为 NoTransforms)
+    pub fn filter(self, policy: MetadataPolicy, orientation: Orientation) -> Self {
+        let orientation_tag = (orientation != Orientation::NoTransforms).then_some(orientation);
+        match policy {
+            MetadataPolicy::KeepAll => {
+                // 方向只可能来自原 EXIF, 有 EXIF 时改写其中的标记即可
+                let exif = match self.exif {
+                    Some(mut exif) => {
+                        set_orientation(&mut exif, orientation);
+                        Some(exif)
+                    }
+                    None => build_exif(orientation_tag, None),
+                };
+                Metadata { exif, ..self }
+            }
+            MetadataPolicy::IccAndCopyright => Metadata {
+                icc: self.icc,
+                exif: build_exif(
+                    orientation_tag,
+                    self.exif.as_deref().and_then(read_copyright),
+                ),
+                xmp: None,
+            },
+            MetadataPolicy::StripAll => Metadata {
+                exif: build_exif(orientation_tag, None),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// EXIF 中记录的方向, 用于解码器自己不读 EXIF 的格式 (PNG)
+    pub fn orientation(&self) -> Orientation {
+        self.exif
+            .as_deref()
+            .and_then(|exif| {
+                let (big_endian, entries) = ifd0_entries(exif)?;
+                let entry = entries
+                    .into_iter()
+                    .find(|&entry| read_u16(exif, entry, big_endian) == Some(0x0112))?;
+                let value = read_u16(exif, entry + 8, big_endian)?;
+                Orientation::from_exif(value.try_into().ok()?)
+            })
+            .unwrap_or(Orientation::NoTransforms)
+    }
+
+    /// 详情中显示的常用 EXIF 字段, 包括 Exif 子 IFD 中的拍摄参数
+    pub fn exif_fields(&self) -> Vec<(&'static str, String)> {
+        let Some((exif, (big_endian, mut entries))) = self
+            .exif
+            .as_deref()
+            .and_then(|exif| Some((exif, ifd0_entries(exif)?)))
+        else {
+            return Vec::new();
+        };
+        let find = |entries: &[usize], tag: u16| {
+            entries
+                .iter()
+                .copied()
+                .find(|&entry| read_u16(exif, entry, big_endian) == Some(tag))
+        };
+        if let Some(sub_entries) = find(&entries, 0x8769)
+            .and_then(|entry| read_u32(exif, entry + 8, big_endian))
+            .and_then(|offset| ifd_entries(exif, offset as usize, big_endian))
+        {
+            entries.extend(sub_entries);
+        }
+
+        EXIF_FIELDS
+            .iter()
+            .filter_map(|&(tag, label)| {
+                let entry = find(&entries, tag)?;
+                let value = entry_value(exif, entry, big_endian)?;
+                let value = match read_u16(exif, entry + 2, big_endian)? {
+                    2 => {
+                        let value = String::from_utf8_lossy(value);
+                        let value = value.trim_end_matches('\0').trim();
+                        (!value.is_empty()).then(|| value.to_string())?
+                    }
+                    3 => read_u16(value, 0, big_endian)?.to_string(),
+                    4 => read_u32(value, 0, big_endian)?.to_string(),
+                    5 => {
+                        let numerator = read_u32(value, 0, big_endian)?;
+                        let denominator = read_u32(value, 4, big_endian)?;
+                        // 分母为 0 时无法换算, 显示原始值
+                        if denominator == 0 {
+                            return Some((tr(label), format!("{numerator}/{denominator}")));
+                        }
+                        let value = numerator as f64 / denominator as f64;
+                        match tag {
+                            0x829A if value > 0.0 && value < 1.0 => {
+                                format!("1/{:.0} s", 1.0 / value)
+                            }
+                            0x829A => format!("{value} s"),
+                            0x829D => format!("f/{value:.1}"),
+                            0x920A => format!("{value:.0} mm"),
+                            _ => format!("{value}"),
+                        }
+                    }
+                    _ => return None,
+                };
+                Some((tr(label), value))
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icc.is_none() && self.exif.is_none() && self.xmp.is_none()
+    }
+
+    /// 结果中显示的摘要, 例如 "ICC, EXIF"
+    pub fn summary(&self) -> String {
+        let kinds = [
+            self.icc.as_ref().map(|_| "ICC"),
+            self.exif.as_ref().map(|_| "EXIF"),
+            self.xmp.as_ref().map(|_| "XMP"),
+        ];
+        let kinds = kinds.into_iter().flatten().collect::<Vec<_>>();
+        if kinds.is_empty() {
+            tr("无").to_string()
+        } else {
+            kinds.join(", ")
+        }
+    }
+}
+
+/// 编码并写入元数据, JPEG 需要事先去掉 alpha, 16 位 AVIF 按 10 位编码
+///
+/// `quality` (1 - 100) 只作用于 JPEG 和 AVIF, WEBP 总是无损
+///
+/// 返回因超出大小限制而丢弃元数据的提示
+pub fn save_with_metadata(
+    image: &DynamicImage,
+    path: &Path,
+    format: ImageFormat,
+    metadata: &Metadata,
+    quality: u8,
+) -> Result<Vec<String>> {
+    let file =
+        std::fs::File::create(path).with_context(|| format!("Failed to create file '{path:?}'"))?;
+    let mut writer = BufWriter::new(file);
+    let icc = metadata.icc.clone();
+    match format {
+        ImageFormat::Png => encode(image, PngEncoder::new(writer), icc)?,
+        ImageFormat::Jpeg => encode(image, JpegEncoder::new_with_quality(writer, quality), icc)?,
+        ImageFormat::WebP => encode(image, WebPEncoder::new_lossless(writer), icc)?,
+        ImageFormat::Avif if depth::bit_depth(image.color()) > 8 => {
+            depth::write_avif_10bit(image, writer, quality)?
+        }
+        ImageFormat::Avif => {
+            image.write_with_encoder(AvifEncoder::new_with_speed_quality(writer, 4, quality))?
+        }
+        _ => image.write_to(&mut writer, format)?,
+    }
+
+    let mut notes = Vec::new();
+    let mut exif = metadata.exif.as_deref();
+    let mut xmp = metadata.xmp.as_deref();
+    // JPEG 的一个 APP1 段最多 65533 字节, 不写扩展 XMP, 超出时整块丢弃
+    if format == ImageFormat::Jpeg {
+        for (kind, header, payload) in [
+            ("EXIF", JPEG_EXIF_HEADER, &mut exif),
+            ("XMP", JPEG_XMP_HEADER, &mut xmp),
+        ] {
+            if payload.is_some_and(|payload| header.len() + payload.len() + 2 > u16::MAX as usize) {
+                *payload = None;
+                notes.push(trf("{} 超过 JPEG 元数据段的 64 KB 上限, 已丢弃", &[&kind]));
+            }
+        }
+    }
+    if exif.is_some() || xmp.is_some() {
+        embed(path, format, exif, xmp)?;
+    }
+
+    Ok(notes)
+}
+
+fn encode(
+    image: &DynamicImage,
+    mut encoder: impl ImageEncoder,
+    icc: Option<Vec<u8>>,
+) -> Result<()> {
+    if let Some(icc) = icc {
+        encoder.set_icc_profile(icc)?;
+    }
+    image.write_with_encoder(encoder)?;
+
+    Ok(())
+}
+
+/// 从各容器存放 XMP 的位置读取数据包: JPEG 的 APP1 段, PNG 的 iTXt 块, WEBP 的 "XMP " 块, TIFF 的 700 号标签
+fn read_xmp(data: &[u8]) -> Option<Vec<u8>> {
+    if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg_segments(data)
+            .into_iter()
+            .find_map(|(marker, payload)| {
+                (marker == 0xE1).then(|| payload.strip_prefix(JPEG_XMP_HEADER))?
+            })
+            .map(<[u8]>::to_vec)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        png_chunks(data).into_iter().find_map(|(kind, payload)| {
+            let text = payload
+                .strip_prefix(PNG_XMP_KEYWORD)
+                .filter(|_| kind == b"iTXt")?;
+            let (&compressed, text) = text.split_first()?;
+            // 跳过压缩方式, 语言和翻译关键字
+            let mut fields = text.get(1..)?.splitn(3, |&byte| byte == 0);
+            let text = fields.nth(2)?;
+            if compressed == 0 {
+                return Some(text.to_vec());
+            }
+            let mut xmp = Vec::new();
+            flate2::read::ZlibDecoder::new(text)
+                .read_to_end(&mut xmp)
+                .ok()?;
+            Some(xmp)
+        })
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        webp_chunks(data)
+            .into_iter()
+            .find(|(kind, _)| *kind == b"XMP ")
+            .map(|(_, payload)| payload.to_vec())
+    } else {
+        // TIFF 的结构与 EXIF 相同, 偏移都从文件开头算起
+        let (big_endian, entries) = ifd0_entries(data)?;
+        let entry = entries
+            .into_iter()
+            .find(|&entry| read_u16(data, entry, big_endian) == Some(700))?;
+        entry_value(data, entry, big_endian).map(<[u8]>::to_vec)
+    }
+}
+
+fn read_png_exif(data: &[u8]) -> Option<Vec<u8>> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return None;
+    }
+    png_chunks(data)
+        .into_iter()
+        .find(|(kind, _)| *kind == b"eXIf")
+        .map(|(_, payload)| payload.to_vec())
+}
+
+/// JPEG 中 SOS 之前的各个段: (标记, 内容)
+fn jpeg_segments(data: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut segments = Vec::new();
+    let mut offset = 2;
+    while let [0xFF, marker, ..] = data[offset.min(data.len())..] {
+        match marker {
+            // 填充字节和没有长度的标记
+            0xFF => offset += 1,
+            0x01 | 0xD0..=0xD7 => offset += 2,
+            0xDA | 0xD9 => break,
+            _ => {
+                let Some(length) = read_u16(data, offset + 2, true) else {
+                    break;
+                };
+                let Some(payload) = data.get(offset + 4..offset + 2 + length as usize) else {
+                    break;
+                };
+                segments.push((marker, payload));
+                offset += 2 + length as usize;
+            }
+        }
+    }
+    segments
+}
+
+/// PNG 的各个块: (类型, 内容)
+fn png_chunks(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut offset = PNG_SIGNATURE.len();
+    while let Some(length) = read_u32(data, offset, true) {
+        let (Some(kind), Some(payload)) = (
+            data.get(offset + 4..offset + 8),
+            data.get(offset + 8..offset + 8 + length as usize),
+        ) else {
+            break;
+        };
+        chunks.push((kind, payload));
+        offset += length as usize + 12;
+    }
+    chunks
+}
+
+/// WEBP (RIFF) 的各个块: (类型, 内容), 奇数长度的块后有一个填充字节
+fn webp_chunks(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while let Some(length) = read_u32(data, offset + 4, false) {
+        let (Some(kind), Some(payload)) = (
+            data.get(offset..offset + 4),
+            data.get(offset + 8..offset + 8 + length as usize),
+        ) else {
+            break;
+        };
+        chunks.push((kind, payload));
+        offset += 8 + length as usize + length as usize % 2;
+    }
+    chunks
+}
+
+/// 解析 EXIF 的 IFD0, 返回 (是否大端, 各条目的起始位置)
+fn ifd0_entries(exif: &[u8]) -> Option<(bool, Vec<usize>)> {
+    let big_endian = match exif.get(..4)? {
+        b"MM\0\x2a" => true,
+        b"II\x2a\0" => false,
+        _ => return None,
+    };
+    let offset = read_u32(exif, 4, big_endian)? as usize;
+    Some((big_endian, ifd_entries(exif, offset, big_endian)?))
+}
+
+fn ifd_entries(exif: &[u8], offset: usize, big_endian: bool) -> Option<Vec<usize>> {
+    let count = read_u16(exif, offset, big_endian)? as usize;
+    let entries = (0..count)
+        .map(|i| offset + 2 + i * 12)
+        .take_while(|entry| entry + 12 <= exif.len())
+        .collect();
+    Some(entries)
+}
+
+/// 条目的值, 不超过 4 字节时直接存放在条目中, 否则条目中是偏移
+fn entry_value(exif: &[u8], entry: usize, big_endian: bool) -> Option<&[u8]> {
+    let size = match read_u16(exif, entry + 2, big_endian)? {
+        1 | 2 | 6 | 7 => 1,
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        _ => 8,
+    } * read_u32(exif, entry + 4, big_endian)? as usize;
+    if size <= 4 {
+        exif.get(entry + 8..entry + 8 + size)
+    } else {
+        let offset = read_u32(exif, entry + 8, big_endian)? as usize;
+        exif.get(offset..offset + size)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+/// 改写 IFD0 中已有的方向标记
+fn set_orientation(exif: &mut [u8], orientation: Orientation) {
+    let Some((big_endian, entries)) = ifd0_entries(exif) else {
+        return;
+    };
+    let Some(entry) = entries
+        .into_iter()
+        .find(|&entry| read_u16(exif, entry, big_endian) == Some(0x0112))
+    else {
+        return;
+    };
+
+    let value = orientation.to_exif() as u16;
+    let value = if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    };
+    exif[entry + 8..entry + 10].copy_from_slice(&value);
+}
+
+/// IFD0 中的 Copyright (0x8298), 含结尾的 \0
+fn read_copyright(exif: &[u8]) -> Option<Vec<u8>> {
+    let (big_endian, entries) = ifd0_entries(exif)?;
+    let entry = entries
+        .into_iter()
+        .find(|&entry| read_u16(exif, entry, big_endian) == Some(0x8298))?;
+    let value = entry_value(exif, entry, big_endian)?;
+
+    (value.first() != Some(&0)).then(|| value.to_vec())
+}
+
+/// 构造只含方向和版权的 EXIF (大端 TIFF 结构), 两者都没有时返回 None
+fn build_exif(orientation: Option<Orientation>, copyright: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    if orientation.is_none() && copyright.is_none() {
+        return None;
+    }
+
+    let count = orientation.is_some() as u16 + copyright.is_some() as u16;
+    // 版权字符串放在 IFD 之后: 头 8 + 条目数 2 + 条目 12n + 下一个 IFD 4
+    let data_offset = 8 + 2 + 12 * count as u32 + 4;
+
+    let mut exif = Vec::new();
+    exif.extend_from_slice(b"MM\0\x2a");
+    exif.extend_from_slice(&8u32.to_be_bytes());
+    exif.extend_from_slice(&count.to_be_bytes());
+    // 条目按 tag 升序: 0x0112 类型 SHORT, 0x8298 类型 ASCII
+    if let Some(orientation) = orientation {
+        exif.extend_from_slice(&0x0112u16.to_be_bytes());
+        exif.extend_from_slice(&3u16.to_be_bytes());
+        exif.extend_from_slice(&1u32.to_be_bytes());
+        exif.extend_from_slice(&[0, orientation.to_exif(), 0, 0]);
+    }
+    if let Some(copyright) = &copyright {
+        exif.extend_from_slice(&0x8298u16.to_be_bytes());
+        exif.extend_from_slice(&2u16.to_be_bytes());
+        exif.extend_from_slice(&(copyright.len() as u32).to_be_bytes());
+        if copyright.len() <= 4 {
+            let mut value = copyright.clone();
+            value.resize(4, 0);
+            exif.extend_from_slice(&value);
+        } else {
+            exif.extend_from_slice(&data_offset.to_be_bytes());
+        }
+    }
+    exif.extend_from_slice(&0u32.to_be_bytes());
+    if let Some(copyright) = copyright.filter(|copyright| copyright.len() > 4) {
+        exif.extend_from_slice(&copyright);
+    }
+
+    Some(exif)
+}
+
+/// 把 EXIF/XMP 写入已编码的文件
+fn embed(path: &Path, format: ImageFormat, exif: Option<&[u8]>, xmp: Option<&[u8]>) -> Result<()> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read file '{path:?}'"))?;
+    let data = match format {
+        ImageFormat::Jpeg => jpeg_with_metadata(&data, exif, xmp)?,
+        ImageFormat::Png => png_with_metadata(&data, exif, xmp)?,
+        ImageFormat::WebP => webp_with_metadata(&data, exif, xmp)?,
+        _ => anyhow::bail!("Cannot write metadata to {format:?}"),
+    };
+    std::fs::write(path, data).with_context(|| format!("Failed to write file '{path:?}'"))
+}
+
+fn jpeg_with_metadata(data: &[u8], exif: Option<&[u8]>, xmp: Option<&[u8]>) -> Result<Vec<u8>> {
+    anyhow::ensure!(data.starts_with(&[0xFF, 0xD8]), "Not a JPEG file");
+
+    // JFIF 要求 APP0 紧跟 SOI, APP1 放在 APP0 之后
+    let mut insert_at = 2;
+    while data.get(insert_at..insert_at + 2) == Some(&[0xFF, 0xE0]) {
+        let length = read_u16(data, insert_at + 2, true).context("Truncated JPEG file")?;
+        insert_at += 2 + length as usize;
+    }
+    anyhow::ensure!(insert_at <= data.len(), "Truncated JPEG file");
+
+    let mut output = Vec::with_capacity(data.len());
+    output.extend_from_slice(&data[..insert_at]);
+    let segments = [
+        exif.map(|exif| (JPEG_EXIF_HEADER, exif)),
+        xmp.map(|xmp| (JPEG_XMP_HEADER, xmp)),
+    ];
+    for (header, payload) in segments.into_iter().flatten() {
+        let length = u16::try_from(header.len() + payload.len() + 2)
+            .context("Metadata too large for a JPEG segment")?;
+        output.extend_from_slice(&[0xFF, 0xE1]);
+        output.extend_from_slice(&length.to_be_bytes());
+        output.extend_from_slice(header);
+        output.extend_from_slice(payload);
+    }
+    output.extend_from_slice(&data[insert_at..]);
+    Ok(output)
+}
+
+fn png_with_metadata(data: &[u8], exif: Option<&[u8]>, xmp: Option<&[u8]>) -> Result<Vec<u8>> {
+    anyhow::ensure!(data.starts_with(PNG_SIGNATURE), "Not a PNG file");
+
+    // eXIf 必须位于 IDAT 之前, 直接放在 IHDR 之后
+    let ihdr_end = PNG_SIGNATURE.len() + 8 + 13 + 4;
+    anyhow::ensure!(data.len() > ihdr_end, "Truncated PNG file");
+
+    let mut output = Vec::with_capacity(data.len());
+    output.extend_from_slice(&data[..ihdr_end]);
+    if let Some(exif) = exif {
+        output.extend_from_slice(&png_chunk(b"eXIf", exif));
+    }
+    if let Some(xmp) = xmp {
+        // 未压缩的 iTXt: 关键字, 压缩标记, 压缩方式, 语言, 翻译关键字
+        let mut payload = PNG_XMP_KEYWORD.to_vec();
+        payload.extend_from_slice(&[0, 0, 0, 0]);
+        payload.extend_from_slice(xmp);
+        output.extend_from_slice(&png_chunk(b"iTXt", &payload));
+    }
+    output.extend_from_slice(&data[ihdr_end..]);
+    Ok(output)
+}
+
+fn png_chunk(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(payload.len() + 12);
+    chunk.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(payload);
+    chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+    chunk
+}
+
+fn webp_with_metadata(data: &[u8], exif: Option<&[u8]>, xmp: Option<&[u8]>) -> Result<Vec<u8>> {
+    anyhow::ensure!(
+        data.len() >= 30 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP",
+        "Not a WebP file"
+    );
+
+    let flags = exif.map_or(0, |_| 0x08) | xmp.map_or(0, |_| 0x04);
+    let mut chunks = data[12..].to_vec();
+    match &chunks[..4] {
+        b"VP8X" => chunks[8] |= flags,
+        // 简单格式没有 VP8X, 需要补上并写入画布尺寸
+        kind @ (b"VP8L" | b"VP8 ") => {
+            let (width, height, alpha) = if kind == b"VP8L" {
+                let bits = u32::from_le_bytes([chunks[9], chunks[10], chunks[11], chunks[12]]);
+                (
+                    (bits & 0x3FFF) + 1,
+                    ((bits >> 14) & 0x3FFF) + 1,
+                    (bits >> 28) & 1 == 1,
+                )
+            } else {
+                let width = u16::from_le_bytes([chunks[14], chunks[15]]) & 0x3FFF;
+                let height = u16::from_le_bytes([chunks[16], chunks[17]]) & 0x3FFF;
+                (width as u32, height as u32, false)
+            };
+
+            let mut vp8x = Vec::with_capacity(18);
+            vp8x.extend_from_slice(b"VP8X");
+            vp8x.extend_from_slice(&10u32.to_le_bytes());
+            vp8x.push(flags | if alpha { 0x10 } else { 0 });
+            vp8x.extend_from_slice(&[0, 0, 0]);
+            vp8x.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+            vp8x.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+            chunks.splice(0..0, vp8x);
+        }
+        _ => anyhow::bail!("Unknown WebP chunk layout"),
+    }
+
+    for (kind, payload) in [(b"EXIF", exif), (b"XMP ", xmp)] {
+        let Some(payload) = payload else { continue };
+        chunks.extend_from_slice(kind);
+        chunks.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunks.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunks.push(0);
+        }
+    }
+
+    let mut output = Vec::with_capacity(chunks.len() + 12);
+    output.extend_from_slice(b"RIFF");
+    output.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    output.extend_from_slice(b"WEBP");
+    output.extend_from_slice(&chunks);
+    Ok(output)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageReader, RgbImage};
+
+    use super::*;
+    use crate::testing::TempFile;
+
+    const XMP: &[u8] = b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF/></x:xmpmeta>";
+
+    /// 写入带 EXIF 和 XMP 的文件, 再按转换时的方式读回
+    fn round_trip(format: ImageFormat, metadata: &Metadata) -> (Vec<u8>, Metadata, Vec<String>) {
+        let path = TempFile::new(format.extensions_str()[0]);
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, image::Rgb([200, 100, 50])));
+        let notes = save_with_metadata(&image, &path, format, metadata, 90).unwrap();
+
+        let mut decoder = ImageReader::open(&path)
+            .unwrap()
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        let read = Metadata::read(&mut decoder, &path).unwrap();
+        (std::fs::read(&path).unwrap(), read, notes)
+    }
+
+    fn sample() -> Metadata {
+        Metadata {
+            icc: None,
+            exif: build_exif(Some(Orientation::Rotate90), Some(b"ImgZap\0".to_vec())),
+            xmp: Some(XMP.to_vec()),
+        }
+    }
+
+    #[test]
+    fn jpeg_round_trip() {
+        let metadata = sample();
+        let (data, read, notes) = round_trip(ImageFormat::Jpeg, &metadata);
+        assert!(notes.is_empty());
+        assert_eq!(read.exif, metadata.exif);
+        assert_eq!(read.xmp, metadata.xmp);
+        assert_eq!(read.orientation(), Orientation::Rotate90);
+
+        // APP0 (JFIF) 仍然紧跟 SOI
+        let segments = jpeg_segments(&data);
+        assert_eq!(segments[0].0, 0xE0);
+        assert!(segments[0].1.starts_with(b"JFIF\0"));
+    }
+
+    #[test]
+    fn jpeg_drops_oversized_xmp() {
+        let metadata = Metadata {
+            xmp: Some(vec![b' '; 70_000]),
+            ..sample()
+        };
+        let (_, read, notes) = round_trip(ImageFormat::Jpeg, &metadata);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(read.exif, metadata.exif);
+        assert_eq!(read.xmp, None);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let metadata = sample();
+        let (_, read, notes) = round_trip(ImageFormat::Png, &metadata);
+        assert!(notes.is_empty());
+        assert_eq!(read.exif, metadata.exif);
+        assert_eq!(read.xmp, metadata.xmp);
+    }
+
+    #[test]
+    fn webp_round_trip() {
+        let metadata = sample();
+        let (_, read, notes) = round_trip(ImageFormat::WebP, &metadata);
+        assert!(notes.is_empty());
+        assert_eq!(read.exif, metadata.exif);
+        assert_eq!(read.xmp, metadata.xmp);
+    }
+
+    #[test]
+    fn zero_denominator_shows_raw_value() {
+        // 曝光时间 1/0
+        let mut exif = b"MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        exif.extend_from_slice(&[0x82, 0x9A, 0, 5, 0, 0, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0]);
+        let metadata = Metadata {
+            exif: Some(exif),
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata.exif_fields(),
+            [(tr("曝光时间"), "1/0".to_string())]
+        );
+    }
+
+    #[test]
+    fn xmp_only_from_container() {
+        // 像素数据里碰巧出现的 "<x:xmpmeta" 不算 XMP
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xDA, 0, 2];
+        data.extend_from_slice(XMP);
+        assert_eq!(read_xmp(&data), None);
+    }
+}
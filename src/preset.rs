@@ -0,0 +1,141 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ImageFormatExt;
+use crate::compress::CompressOptions;
+use crate::convert::ConvertOptions;
+use crate::i18n::{self, Language, tr};
+use crate::resize::{ResizeKind, ResizeOptions};
+
+/// 内置预设的标识和中文名称, 名称中注明实际的格式: WEBP 只能无损编码, 也没有 ICNS 编码器
+const BUILTIN_NAMES: [(&str, &str); 3] = [
+    ("web", "网页导出 (WEBP 无损 + AVIF 质量 60)"),
+    ("app-icon", "应用图标 (ICO + APP ICON, 不含 ICNS)"),
+    ("png-lossless", "PNG 无损压缩"),
+];
+
+/// 早期版本的内置预设名称, 按名称推断标识时仍然认得
+const LEGACY_NAMES: [(&str, &str); 2] = [("web", "网页导出"), ("app-icon", "应用图标")];
+
+/// 命名的转换设置: 目标格式加上全部转换选项 (缩放, 命名, 输出文件夹等)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    /// 不随界面语言变化的标识, 内置预设为固定值, 保存的预设为首次保存时的名称
+    #[serde(default)]
+    pub id: String,
+    /// 显示的名称, 内置预设为当前语言的名称
+    pub name: String,
+    pub formats: Vec<ImageFormatExt>,
+    #[serde(default)]
+    pub options: ConvertOptions,
+}
+
+impl Preset {
+    /// 旧版本保存的预设没有标识, 按名称推断
+    pub fn fill_id(&mut self) {
+        if self.id.is_empty() {
+            self.id = builtin_id(&self.name)
+                .map(str::to_string)
+                .unwrap_or_else(|| self.name.clone());
+        }
+    }
+
+    /// 标识或任意语言的名称相同
+    pub fn matches(&self, query: &str) -> bool {
+        self.id == query || self.name == query || builtin_id(query) == Some(self.id.as_str())
+    }
+}
+
+impl PartialEq for Preset {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// 名称为任意语言的内置预设名称时返回其标识
+fn builtin_id(name: &str) -> Option<&'static str> {
+    BUILTIN_NAMES
+        .iter()
+        .chain(&LEGACY_NAMES)
+        .find_map(|(id, zh)| {
+            Language::ALL
+                .iter()
+                .any(|language| i18n::translate(*language, zh) == name)
+                .then_some(*id)
+        })
+}
+
+fn builtin_name(id: &str) -> &'static str {
+    let (_, name) = BUILTIN_NAMES.iter().find(|(i, _)| *i == id).unwrap();
+    tr(name)
+}
+
+/// 内置预设
+pub fn builtin() -> Vec<Preset> {
+    let preset = |id: &str, formats, options| Preset {
+        id: id.to_string(),
+        name: builtin_name(id).to_string(),
+        formats,
+        options,
+    };
+
+    vec![
+        preset(
+            "web",
+            // WEBP 编码器只支持无损, 质量只作用于 AVIF
+            vec![ImageFormatExt::WebP, ImageFormatExt::Avif],
+            ConvertOptions {
+                quality: 60,
+                resize: ResizeOptions {
+                    kind: ResizeKind::LongEdge,
+                    edge: 1920,
+                    no_upscale: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ),
+        preset(
+            "app-icon",
+            vec![ImageFormatExt::Ico, ImageFormatExt::AppIcon],
+            ConvertOptions::default(),
+        ),
+        preset(
+            "png-lossless",
+            vec![ImageFormatExt::Png],
+            ConvertOptions {
+                compress: CompressOptions {
+                    enabled: true,
+                    level: 4,
+                },
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+/// 内置预设在前, 保存的同标识预设替换内置预设的设置, 名称仍随界面语言
+pub fn all(saved: &[Preset]) -> Vec<Preset> {
+    let mut presets = builtin();
+    for preset in saved {
+        let preset = preset.clone();
+        match presets.iter_mut().find(|p| p.id == preset.id) {
+            Some(builtin) => {
+                *builtin = Preset {
+                    name: builtin.name.clone(),
+                    ..preset
+                }
+            }
+            None => presets.push(preset),
+        }
+    }
+
+    presets
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+
+use image::{DynamicImage, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeKind {
+    None,
+    /// 缩小到不超过 宽 x 高, 为 0 的一边不限制
+    MaxSize,
+    Exact,
+    /// 百分比为 0 时不缩放
+    Percent,
+    /// 长边/短边为 0 时不缩放
+    LongEdge,
+    ShortEdge,
+}
+
+/// 指定宽高时的缩放方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeFit {
+    Stretch,
+    /// 等比缩放到宽高以内
+    Contain,
+    /// 等比缩放铺满宽高, 按锚点裁掉超出部分
+    Cover,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CropAnchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResizeOptions {
+    pub kind: ResizeKind,
+    pub width: u32,
+    pub height: u32,
+    pub percent: u32,
+    /// 长边/短边的像素
+    pub edge: u32,
+    pub fit: ResizeFit,
+    pub anchor: CropAnchor,
+    pub no_upscale: bool,
+    pub filter: ResizeFilter,
+}
+
+impl Default for ResizeOptions {
+    fn default() -> Self {
+        ResizeOptions {
+            kind: ResizeKind::None,
+            width: 1920,
+            height: 1080,
+            percent: 50,
+            edge: 1920,
+            fit: ResizeFit::Contain,
+            anchor: CropAnchor::Center,
+            no_upscale: true,
+            filter: ResizeFilter::Lanczos3,
+        }
+    }
+}
+
+impl ResizeOptions {
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        let filter = self.filter.filter_type();
+        let limit = |scale: f64| {
+            if self.no_upscale {
+                scale.min(1.0)
+            } else {
+                scale
+            }
+        };
+
+        let scale = match self.kind {
+            ResizeKind::None => return image,
+            ResizeKind::MaxSize => {
+                let scale_x = match self.width {
+                    0 => 1.0,
+                    w => w as f64 / width,
+                };
+                let scale_y = match self.height {
+                    0 => 1.0,
+                    h => h as f64 / height,
+                };
+                scale_x.min(scale_y).min(1.0)
+            }
+            // 输入框清空时为 0, 当作不缩放
+            ResizeKind::Percent if self.percent == 0 => return image,
+            ResizeKind::LongEdge | ResizeKind::ShortEdge if self.edge == 0 => return image,
+            ResizeKind::Percent => limit(self.percent as f64 / 100.0),
+            ResizeKind::LongEdge => limit(self.edge as f64 / width.max(height)),
+            ResizeKind::ShortEdge => limit(self.edge as f64 / width.min(height)),
+            ResizeKind::Exact => {
+                if self.width == 0 || self.height == 0 {
+                    return image;
+                }
+                let scale_x = self.width as f64 / width;
+                let scale_y = self.height as f64 / height;
+                match self.fit {
+                    ResizeFit::Stretch => {
+                        let new_width = (width * limit(scale_x)).round().max(1.0) as u32;
+                        let new_height = (height * limit(scale_y)).round().max(1.0) as u32;
+                        return image.resize_exact(new_width, new_height, filter);
+                    }
+                    ResizeFit::Contain => limit(scale_x.min(scale_y)),
+                    ResizeFit::Cover => {
+                        let scale = limit(scale_x.max(scale_y));
+                        let new_width = (width * scale).round().max(1.0) as u32;
+                        let new_height = (height * scale).round().max(1.0) as u32;
+                        let image = if scale == 1.0 {
+                            image
+                        } else {
+                            image.resize_exact(new_width, new_height, filter)
+                        };
+                        let crop_width = self.width.min(new_width);
+                        let crop_height = self.height.min(new_height);
+                        let (x, y) = self
+                            .anchor
+                            .offset(new_width - crop_width, new_height - crop_height);
+                        return image.crop_imm(x, y, crop_width, crop_height);
+                    }
+                }
+            }
+        };
+
+        if scale == 1.0 {
+            return image;
+        }
+        let new_width = (width * scale).round().max(1.0) as u32;
+        let new_height = (height * scale).round().max(1.0) as u32;
+        image.resize_exact(new_width, new_height, filter)
+    }
+}
+
+impl ResizeKind {
+    pub const ALL: [ResizeKind; 6] = [
+        ResizeKind::None,
+        ResizeKind::MaxSize,
+        ResizeKind::Exact,
+        ResizeKind::Percent,
+        ResizeKind::LongEdge,
+        ResizeKind::ShortEdge,
+    ];
+}
+
+impl fmt::Display for ResizeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            ResizeKind::None => "不缩放",
+            ResizeKind::MaxSize => "最大宽高",
+            ResizeKind::Exact => "指定宽高",
+            ResizeKind::Percent => "百分比",
+            ResizeKind::LongEdge => "长边",
+            ResizeKind::ShortEdge => "短边",
+        }))
+    }
+}
+
+impl ResizeFit {
+    pub const ALL: [ResizeFit; 3] = [ResizeFit::Stretch, ResizeFit::Contain, ResizeFit::Cover];
+}
+
+impl fmt::Display for ResizeFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            ResizeFit::Stretch => "拉伸",
+            ResizeFit::Contain => "适应",
+            ResizeFit::Cover => "填充裁剪",
+        }))
+    }
+}
+
+impl CropAnchor {
+    pub const ALL: [CropAnchor; 9] = [
+        CropAnchor::TopLeft,
+        CropAnchor::Top,
+        CropAnchor::TopRight,
+        CropAnchor::Left,
+        CropAnchor::Center,
+        CropAnchor::Right,
+        CropAnchor::BottomLeft,
+        CropAnchor::Bottom,
+        CropAnchor::BottomRight,
+    ];
+
+    /// 在多余的宽高中取裁剪起点
+    fn offset(&self, extra_width: u32, extra_height: u32) -> (u32, u32) {
+        let x = match self {
+            CropAnchor::TopLeft | CropAnchor::Left | CropAnchor::BottomLeft => 0,
+            CropAnchor::Top | CropAnchor::Center | CropAnchor::Bottom => extra_width / 2,
+            CropAnchor::TopRight | CropAnchor::Right | CropAnchor::BottomRight => extra_width,
+        };
+        let y = match self {
+            CropAnchor::TopLeft | CropAnchor::Top | CropAnchor::TopRight => 0,
+            CropAnchor::Left | CropAnchor::Center | CropAnchor::Right => extra_height / 2,
+            CropAnchor::BottomLeft | CropAnchor::Bottom | CropAnchor::BottomRight => extra_height,
+        };
+        (x, y)
+    }
+}
+
+impl fmt::Display for CropAnchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            CropAnchor::TopLeft => "左上",
+            CropAnchor::Top => "上",
+            CropAnchor::TopRight => "右上",
+            CropAnchor::Left => "左",
+            CropAnchor::Center => "居中",
+            CropAnchor::Right => "右",
+            CropAnchor::BottomLeft => "左下",
+            CropAnchor::Bottom => "下",
+            CropAnchor::BottomRight => "右下",
+        }))
+    }
+}
+
+impl ResizeFilter {
+    pub const ALL: [ResizeFilter; 5] = [
+        ResizeFilter::Nearest,
+        ResizeFilter::Triangle,
+        ResizeFilter::CatmullRom,
+        ResizeFilter::Gaussian,
+        ResizeFilter::Lanczos3,
+    ];
+
+    pub fn filter_type(&self) -> FilterType {
+        match self {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl fmt::Display for ResizeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ResizeFilter::Nearest => "Nearest",
+            ResizeFilter::Triangle => "Triangle",
+            ResizeFilter::CatmullRom => "CatmullRom",
+            ResizeFilter::Gaussian => "Gaussian",
+            ResizeFilter::Lanczos3 => "Lanczos3",
+        })
+    }
+}
@@ -0,0 +1,749 @@
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use rayon::prelude::*;
+use resvg::{tiny_skia, usvg};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use crate::i18n::{tr, trf};
+
+// 加载系统字体很慢, 所有 SVG 共用同一份字体库
+static SYSTEM_FONTS: LazyLock<Arc<usvg::fontdb::Database>> = LazyLock::new(|| {
+    let mut fontdb = usvg::fontdb::Database::new();
+    fontdb.load_system_fonts();
+    Arc::new(fontdb)
+});
+
+// (字体目录, 默认字体, 字体库)
+type CustomFonts = (Vec<PathBuf>, String, Arc<usvg::fontdb::Database>);
+
+// 带自定义字体目录/默认字体的字体库, 设置不变时复用
+static CUSTOM_FONTS: Mutex<Option<CustomFonts>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SvgSize {
+    /// SVG 自身的尺寸 (96 DPI)
+    Original,
+    Width(u32),
+    Height(u32),
+    Exact(u32, u32),
+    Scale(f32),
+    Dpi(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SvgSizeKind {
+    Original,
+    Width,
+    Height,
+    Exact,
+    Scale,
+    Dpi,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SvgFit {
+    /// 等比缩放, 完整放入目标尺寸
+    Contain,
+    /// 等比缩放, 铺满目标尺寸并裁掉超出部分
+    Cover,
+    /// 宽高分别缩放
+    Stretch,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SvgOptions {
+    pub size: SvgSize,
+    pub fit: SvgFit,
+    /// 一次导出多个尺寸, 为空时只按 `size` 导出
+    pub sizes: Vec<SvgSize>,
+    /// 四周额外留白的像素
+    pub padding: u32,
+    pub background: Option<[u8; 4]>,
+    /// 除系统字体外额外加载的字体目录
+    pub font_dirs: Vec<PathBuf>,
+    /// 未指定字体或字体未安装时使用的字体, 为空则使用 usvg 的默认值
+    pub default_font_family: String,
+    /// 不加载 `<image href>` 引用的外部文件, 仅允许 data URL
+    pub block_external: bool,
+    pub optimize: SvgOptimize,
+}
+
+/// SVG 精简: 输入 SVG 输出 `name.min.svg`, 也用于矢量化生成的 SVG
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SvgOptimize {
+    pub enabled: bool,
+    /// 坐标保留的小数位数
+    pub precision: u8,
+    /// 把相邻且样式相同、互不重叠的路径合并为一个
+    pub merge_paths: bool,
+    /// 去掉缩进和换行
+    pub minify: bool,
+}
+
+impl Default for SvgOptimize {
+    fn default() -> Self {
+        SvgOptimize {
+            enabled: false,
+            precision: 2,
+            merge_paths: true,
+            minify: true,
+        }
+    }
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            size: SvgSize::Exact(256, 256),
+            fit: SvgFit::Contain,
+            sizes: Vec::new(),
+            padding: 0,
+            background: None,
+            font_dirs: Vec::new(),
+            default_font_family: String::new(),
+            block_external: false,
+            optimize: SvgOptimize::default(),
+        }
+    }
+}
+
+impl SvgOptions {
+    /// 尺寸为 0 或不是有效数字时改回默认值, 精度最多 6 位
+    pub fn sanitize(&mut self) {
+        if !self.size.is_valid() {
+            self.size = SvgOptions::default().size;
+        }
+        self.sizes.retain(SvgSize::is_valid);
+        self.optimize.precision = self.optimize.precision.min(6);
+    }
+}
+
+impl SvgSize {
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            SvgSize::Original => true,
+            SvgSize::Width(v) | SvgSize::Height(v) => v > 0,
+            SvgSize::Exact(w, h) => w > 0 && h > 0,
+            SvgSize::Scale(v) | SvgSize::Dpi(v) => v.is_finite() && v > 0.0,
+        }
+    }
+
+    pub fn kind(&self) -> SvgSizeKind {
+        match self {
+            SvgSize::Original => SvgSizeKind::Original,
+            SvgSize::Width(_) => SvgSizeKind::Width,
+            SvgSize::Height(_) => SvgSizeKind::Height,
+            SvgSize::Exact(..) => SvgSizeKind::Exact,
+            SvgSize::Scale(_) => SvgSizeKind::Scale,
+            SvgSize::Dpi(_) => SvgSizeKind::Dpi,
+        }
+    }
+
+    /// 按 `kind` 解析输入框内容, 例如 "256", "256x128", "1.5"
+    pub fn parse(kind: SvgSizeKind, text: &str) -> Option<SvgSize> {
+        let text = text.trim();
+        let size = match kind {
+            SvgSizeKind::Original => SvgSize::Original,
+            SvgSizeKind::Width => SvgSize::Width(text.parse().ok().filter(|&w| w > 0)?),
+            SvgSizeKind::Height => SvgSize::Height(text.parse().ok().filter(|&h| h > 0)?),
+            SvgSizeKind::Exact => {
+                let (w, h) = text.split_once(['x', 'X', '×', '*'])?;
+                let w = w.trim().parse().ok().filter(|&w| w > 0)?;
+                let h = h.trim().parse().ok().filter(|&h| h > 0)?;
+                SvgSize::Exact(w, h)
+            }
+            SvgSizeKind::Scale => SvgSize::Scale(text.parse().ok().filter(|&s| s > 0.0)?),
+            SvgSizeKind::Dpi => SvgSize::Dpi(text.parse().ok().filter(|&d| d > 0.0)?),
+        };
+        Some(size)
+    }
+
+    /// 解析多尺寸列表, 例如 "24, 48, 72", "1x 2x 3x", "64x32"
+    pub fn parse_list(text: &str) -> Option<Vec<SvgSize>> {
+        text.split([',', ' ', ';'])
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                if let Some(scale) = item.strip_suffix(['x', 'X']) {
+                    SvgSize::parse(SvgSizeKind::Scale, scale)
+                } else if item.contains(['x', 'X', '×', '*']) {
+                    SvgSize::parse(SvgSizeKind::Exact, item)
+                } else {
+                    let size = item.parse().ok().filter(|&s| s > 0)?;
+                    Some(SvgSize::Exact(size, size))
+                }
+            })
+            .collect()
+    }
+
+    /// parse_list 的逆操作, 用于恢复输入框内容
+    pub fn format_list(sizes: &[SvgSize]) -> String {
+        sizes
+            .iter()
+            .map(|size| match size {
+                SvgSize::Exact(w, h) if w == h => w.to_string(),
+                SvgSize::Scale(s) => format!("{s}x"),
+                size => size.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// 多尺寸导出时附加在文件名后的后缀
+    pub fn suffix(&self) -> String {
+        match self {
+            SvgSize::Original => String::new(),
+            SvgSize::Width(w) => format!("_w{w}"),
+            SvgSize::Height(h) => format!("_h{h}"),
+            SvgSize::Exact(w, h) if w == h => format!("_{w}"),
+            SvgSize::Exact(w, h) => format!("_{w}x{h}"),
+            SvgSize::Scale(s) => format!("@{s}x"),
+            SvgSize::Dpi(d) => format!("_{d}dpi"),
+        }
+    }
+
+    /// 渲染区域的尺寸 (不含留白)
+    fn resolve(&self, width: f32, height: f32) -> (f32, f32) {
+        match *self {
+            SvgSize::Original => (width, height),
+            SvgSize::Width(w) => (w as f32, w as f32 * height / width),
+            SvgSize::Height(h) => (h as f32 * width / height, h as f32),
+            SvgSize::Exact(w, h) => (w as f32, h as f32),
+            SvgSize::Scale(s) => (width * s, height * s),
+            SvgSize::Dpi(d) => (width * d / 96.0, height * d / 96.0),
+        }
+    }
+}
+
+impl fmt::Display for SvgSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgSize::Original => Ok(()),
+            SvgSize::Width(v) | SvgSize::Height(v) => write!(f, "{v}"),
+            SvgSize::Exact(w, h) => write!(f, "{w}x{h}"),
+            SvgSize::Scale(v) | SvgSize::Dpi(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+impl SvgSizeKind {
+    pub const ALL: [SvgSizeKind; 6] = [
+        SvgSizeKind::Original,
+        SvgSizeKind::Width,
+        SvgSizeKind::Height,
+        SvgSizeKind::Exact,
+        SvgSizeKind::Scale,
+        SvgSizeKind::Dpi,
+    ];
+}
+
+impl fmt::Display for SvgSizeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            SvgSizeKind::Original => "原始尺寸",
+            SvgSizeKind::Width => "宽度",
+            SvgSizeKind::Height => "高度",
+            SvgSizeKind::Exact => "宽 x 高",
+            SvgSizeKind::Scale => "缩放倍数",
+            SvgSizeKind::Dpi => "DPI",
+        }))
+    }
+}
+
+impl SvgFit {
+    pub const ALL: [SvgFit; 3] = [SvgFit::Contain, SvgFit::Cover, SvgFit::Stretch];
+}
+
+impl fmt::Display for SvgFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            SvgFit::Contain => "适应",
+            SvgFit::Cover => "填充",
+            SvgFit::Stretch => "拉伸",
+        }))
+    }
+}
+
+/// 解析 "#RRGGBB" 或 "#RRGGBBAA"
+pub fn parse_hex_color(text: &str) -> Option<[u8; 4]> {
+    let hex = text.trim().trim_start_matches('#');
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some([channel(0)?, channel(2)?, channel(4)?, 255]),
+        8 => Some([channel(0)?, channel(2)?, channel(4)?, channel(6)?]),
+        _ => None,
+    }
+}
+
+/// 系统字体库, 第一次调用时加载
+pub fn system_fonts() -> Arc<usvg::fontdb::Database> {
+    SYSTEM_FONTS.clone()
+}
+
+fn font_database(options: &SvgOptions) -> Arc<usvg::fontdb::Database> {
+    let family = options.default_font_family.trim();
+    if options.font_dirs.is_empty() && family.is_empty() {
+        return SYSTEM_FONTS.clone();
+    }
+
+    let mut cache = CUSTOM_FONTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((dirs, cached_family, fontdb)) = cache.as_ref()
+        && *dirs == options.font_dirs
+        && cached_family == family
+    {
+        return fontdb.clone();
+    }
+
+    let mut fontdb = usvg::fontdb::Database::clone(&SYSTEM_FONTS);
+    options
+        .font_dirs
+        .iter()
+        .for_each(|dir| fontdb.load_fonts_dir(dir));
+    // usvg 在找不到字体时回退到 serif
+    if !family.is_empty() {
+        fontdb.set_serif_family(family);
+        fontdb.set_sans_serif_family(family);
+    }
+
+    let fontdb = Arc::new(fontdb);
+    *cache = Some((options.font_dirs.clone(), family.to_owned(), fontdb.clone()));
+    fontdb
+}
+
+/// 解析 SVG, 同时返回缺失或被阻止的外部资源
+pub fn load_svg(input_path: &Path, options: &SvgOptions) -> Result<(usvg::Tree, Vec<String>)> {
+    let unresolved = Mutex::new(Vec::new());
+    let block_external = options.block_external;
+    let default_resolver = usvg::ImageHrefResolver::default_string_resolver();
+
+    let mut opt = usvg::Options {
+        // 相对路径相对于 SVG 所在的文件夹
+        resources_dir: input_path.parent().map(Path::to_path_buf),
+        fontdb: font_database(options),
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: usvg::ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|href, opts| {
+                let image = if block_external {
+                    None
+                } else {
+                    default_resolver(href, opts)
+                };
+                if image.is_none() {
+                    let mut unresolved = unresolved.lock().unwrap_or_else(|e| e.into_inner());
+                    unresolved.push(if block_external {
+                        trf("已阻止外部资源: {}", &[&href])
+                    } else {
+                        trf("找不到引用的资源: {}", &[&href])
+                    });
+                }
+                image
+            }),
+        },
+        ..Default::default()
+    };
+    if !options.default_font_family.trim().is_empty() {
+        opt.font_family = options.default_font_family.trim().to_owned();
+    }
+
+    let svg_data = std::fs::read(input_path)
+        .with_context(|| format!("Failed to read file '{input_path:?}'"))?;
+    let rtree =
+        usvg::Tree::from_data(&svg_data, &opt).with_context(|| "Failed to parse SVG contents")?;
+    drop(opt);
+
+    Ok((
+        rtree,
+        unresolved.into_inner().unwrap_or_else(|e| e.into_inner()),
+    ))
+}
+
+pub fn render_svg(rtree: &usvg::Tree, options: &SvgOptions) -> Result<RgbaImage> {
+    let (svg_width, svg_height) = (rtree.size().width(), rtree.size().height());
+    let (area_width, area_height) = options.size.resolve(svg_width, svg_height);
+    let (scale_x, scale_y) = match options.fit {
+        SvgFit::Stretch => (area_width / svg_width, area_height / svg_height),
+        SvgFit::Contain => {
+            let scale = (area_width / svg_width).min(area_height / svg_height);
+            (scale, scale)
+        }
+        SvgFit::Cover => {
+            let scale = (area_width / svg_width).max(area_height / svg_height);
+            (scale, scale)
+        }
+    };
+
+    // 先渲染到目标区域 (填充模式下超出部分被裁掉), 再贴到带留白和背景的画布上
+    let area_width = area_width.round().max(1.0) as u32;
+    let area_height = area_height.round().max(1.0) as u32;
+    let mut area = tiny_skia::Pixmap::new(area_width, area_height)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create SVG Pixmap!"))?;
+    let transform = tiny_skia::Transform::from_scale(scale_x, scale_y).post_translate(
+        (area_width as f32 - svg_width * scale_x) / 2.0,
+        (area_height as f32 - svg_height * scale_y) / 2.0,
+    );
+    resvg::render(rtree, transform, &mut area.as_mut());
+
+    // 留白是用户输入的, 过大时报错而不是溢出
+    let padded = |size: u32| {
+        options
+            .padding
+            .checked_mul(2)
+            .and_then(|padding| padding.checked_add(size))
+            .ok_or_else(|| anyhow::anyhow!("SVG padding {} is too large", options.padding))
+    };
+    let width = padded(area_width)?;
+    let height = padded(area_height)?;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow::anyhow!("Failed to create SVG Pixmap!"))?;
+    if let Some([r, g, b, a]) = options.background {
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+    }
+    pixmap.draw_pixmap(
+        options.padding as i32,
+        options.padding as i32,
+        area.as_ref(),
+        &tiny_skia::PixmapPaint::default(),
+        tiny_skia::Transform::identity(),
+        None,
+    );
+
+    let mut image = RgbaImage::new(width, height);
+    let buffer = image.as_mut();
+    buffer.par_chunks_mut(4).enumerate().for_each(|(i, chunk)| {
+        let x = (i as u32) % width;
+        let y = (i as u32) / width;
+
+        let pixel = pixmap
+            .pixel(x, y)
+            .unwrap_or(tiny_skia::PremultipliedColorU8::TRANSPARENT)
+            .demultiply();
+
+        chunk[0] = pixel.red();
+        chunk[1] = pixel.green();
+        chunk[2] = pixel.blue();
+        chunk[3] = pixel.alpha();
+    });
+
+    Ok(image)
+}
+
+/// 重新输出 usvg 已经规范化的树: 编辑器元数据、无用分组和样式表在解析时就被去掉了,
+/// 这里再降低坐标精度, 合并路径并压缩路径数据和空白
+pub fn optimize_svg(rtree: &usvg::Tree, optimize: &SvgOptimize) -> String {
+    let svg = rtree.to_string(&usvg::WriteOptions {
+        coordinates_precision: optimize.precision,
+        // 变换矩阵对精度更敏感, 多保留两位
+        transforms_precision: optimize.precision.saturating_add(2),
+        indent: if optimize.minify {
+            usvg::Indent::None
+        } else {
+            usvg::Indent::Spaces(2)
+        },
+        ..Default::default()
+    });
+    let svg = if optimize.merge_paths {
+        merge_paths(rtree, &svg)
+    } else {
+        svg
+    };
+
+    compact_path_data(&svg)
+}
+
+/// 按 usvg 树找出可以合并的相邻路径, 再改写 `to_string` 输出的对应元素
+///
+/// 输出的结构与树不一致时 (usvg 的写法变了) 原样返回
+fn merge_paths(rtree: &usvg::Tree, svg: &str) -> String {
+    let Ok(document) = roxmltree::Document::parse(svg) else {
+        return svg.to_owned();
+    };
+    // 渐变、剪切路径等都写在 defs 中, 之后的元素与根节点的子节点一一对应
+    let elements = document
+        .root_element()
+        .children()
+        .filter(|node| node.is_element() && !node.has_tag_name("defs"))
+        .collect::<Vec<_>>();
+    let mut edits = Vec::new();
+    if !collect_merges(rtree.root(), &elements, svg, &mut edits) {
+        return svg.to_owned();
+    }
+
+    let mut output = svg.to_owned();
+    edits.sort_by_key(|(range, _): &(Range<usize>, String)| range.start);
+    for (range, replacement) in edits.into_iter().rev() {
+        output.replace_range(range, &replacement);
+    }
+
+    output
+}
+
+fn collect_merges(
+    group: &usvg::Group,
+    elements: &[roxmltree::Node],
+    svg: &str,
+    edits: &mut Vec<(Range<usize>, String)>,
+) -> bool {
+    if elements.len() != group.children().len() {
+        return false;
+    }
+
+    // 当前可以合并的一组路径
+    let mut run: Vec<(&usvg::Path, roxmltree::Node)> = Vec::new();
+    for (node, &element) in group.children().iter().zip(elements) {
+        let matched = match node {
+            usvg::Node::Path(path) => {
+                if !element.has_tag_name("path") {
+                    return false;
+                }
+                let mergeable = run.first().is_some_and(|(first, _)| {
+                    same_style(first, path)
+                        && run.iter().all(|(other, _)| {
+                            disjoint(
+                                other.abs_stroke_bounding_box(),
+                                path.abs_stroke_bounding_box(),
+                            )
+                        })
+                });
+                if !mergeable {
+                    flush_run(&run, svg, edits);
+                    run.clear();
+                }
+                run.push((path, element));
+                true
+            }
+            usvg::Node::Group(child) => {
+                element.has_tag_name("g")
+                    && collect_merges(child, &child_elements(element), svg, edits)
+            }
+            // 不保留文字时, 文字按轮廓写成分组
+            usvg::Node::Text(text) => {
+                element.has_tag_name("g")
+                    && collect_merges(text.flattened(), &child_elements(element), svg, edits)
+            }
+            usvg::Node::Image(_) => element.has_tag_name("image"),
+        };
+        if !matched {
+            return false;
+        }
+        if !matches!(node, usvg::Node::Path(_)) {
+            flush_run(&run, svg, edits);
+            run.clear();
+        }
+    }
+    flush_run(&run, svg, edits);
+
+    true
+}
+
+fn child_elements<'a, 'input>(
+    element: roxmltree::Node<'a, 'input>,
+) -> Vec<roxmltree::Node<'a, 'input>> {
+    element
+        .children()
+        .filter(|node| node.is_element())
+        .collect()
+}
+
+/// 路径数据追加到第一个元素上, 删掉其余元素 (连同前面的空白)
+fn flush_run(
+    run: &[(&usvg::Path, roxmltree::Node)],
+    svg: &str,
+    edits: &mut Vec<(Range<usize>, String)>,
+) {
+    let [(_, first), rest @ ..] = run else {
+        return;
+    };
+    if rest.is_empty() {
+        return;
+    }
+    let Some(d) = first.attribute_node("d") else {
+        return;
+    };
+
+    let data = run
+        .iter()
+        .filter_map(|(_, element)| element.attribute("d"))
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ");
+    edits.push((d.range_value(), data));
+    for pair in run.windows(2) {
+        let start = pair[0].1.range().end;
+        let end = pair[1].1.range().end;
+        debug_assert!(svg[start..end].trim_start().starts_with("<path"));
+        edits.push((start..end, String::new()));
+    }
+}
+
+/// 合并后渲染结果不变的条件: 同一变换下填充、描边、绘制顺序都相同,
+/// 只用纯色, 没有虚线 (虚线在每个子路径重新开始) 和 id
+fn same_style(a: &usvg::Path, b: &usvg::Path) -> bool {
+    let same_color = |a: &usvg::Paint, b: &usvg::Paint| match (a, b) {
+        (usvg::Paint::Color(a), usvg::Paint::Color(b)) => a == b,
+        _ => false,
+    };
+    let same_fill = match (a.fill(), b.fill()) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            same_color(a.paint(), b.paint()) && a.opacity() == b.opacity() && a.rule() == b.rule()
+        }
+        _ => false,
+    };
+    let same_stroke = match (a.stroke(), b.stroke()) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            same_color(a.paint(), b.paint())
+                && a.opacity() == b.opacity()
+                && a.width() == b.width()
+                && a.linecap() == b.linecap()
+                && a.linejoin() == b.linejoin()
+                && a.miterlimit() == b.miterlimit()
+                && a.dasharray().is_none()
+                && b.dasharray().is_none()
+        }
+        _ => false,
+    };
+
+    same_fill
+        && same_stroke
+        && a.is_visible()
+        && b.is_visible()
+        && a.id().is_empty()
+        && b.id().is_empty()
+        && a.paint_order() == b.paint_order()
+        && a.rendering_mode() == b.rendering_mode()
+        && a.abs_transform() == b.abs_transform()
+}
+
+/// 重叠的部分合并后会按填充规则重新计算 (可能变成镂空), 半透明时也不会再叠加,
+/// 所以只合并包围盒 (含描边) 互不接触的路径
+fn disjoint(a: tiny_skia::Rect, b: tiny_skia::Rect) -> bool {
+    a.right() < b.left() || b.right() < a.left() || a.bottom() < b.top() || b.bottom() < a.top()
+}
+
+/// 压缩所有 `d` 属性: "M 1 2 L 3 -4 Z" -> "M1 2L3-4Z"
+///
+/// usvg 输出的路径数据只有绝对坐标的 M/L/Q/C/Z 和数字, 属性值中的引号会被转义,
+/// 所以 ` d="` 只会出现在属性开头
+fn compact_path_data(svg: &str) -> String {
+    let mut output = String::with_capacity(svg.len());
+    let mut rest = svg;
+    while let Some(start) = rest.find(" d=\"") {
+        let (head, tail) = rest.split_at(start + " d=\"".len());
+        output.push_str(head);
+        let end = tail.find('"').unwrap_or(tail.len());
+        let mut compact = String::with_capacity(end);
+        for token in tail[..end].split_whitespace() {
+            let is_command = token.starts_with(|c: char| c.is_ascii_alphabetic());
+            let after_command = compact.ends_with(|c: char| c.is_ascii_alphabetic());
+            if !compact.is_empty() && !is_command && !after_command && !token.starts_with('-') {
+                compact.push(' ');
+            }
+            compact.push_str(token);
+        }
+        output.push_str(&compact);
+        rest = &tail[end..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 相对坐标, 圆弧, 曲线, 描边, 渐变, 相互重叠的同样式路径和可以合并的路径
+    const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100">
+  <defs>
+    <linearGradient id="g" x1="0" x2="1">
+      <stop offset="0" stop-color="#f00"/>
+      <stop offset="1" stop-color="#00f"/>
+    </linearGradient>
+  </defs>
+  <path d="m10 10 h30 v30 h-30 z" fill="#0a0" fill-opacity="0.5"/>
+  <path d="m25 25 h30 v30 h-30 z" fill="#0a0" fill-opacity="0.5"/>
+  <path d="M60 20 a15 10 30 1 1 20 20 q-10 10 -20 0 c-5 -5 5 -15 0 -20" fill="url(#g)"/>
+  <path d="M10 80 s20 -20 40 0 t40 0" fill="none" stroke="#333" stroke-width="3"/>
+  <g transform="translate(0 85)">
+    <rect x="5" y="0" width="10" height="10" fill="#800"/>
+    <circle cx="30" cy="5" r="5" fill="#800"/>
+    <rect x="40" y="0" width="10" height="10" fill="#800" opacity="0.5"/>
+    <rect x="60" y="0" width="10" height="10" fill="#080"/>
+  </g>
+</svg>"##;
+
+    fn render(svg: &str) -> RgbaImage {
+        let rtree = usvg::Tree::from_str(svg, &usvg::Options::default()).unwrap();
+        render_svg(
+            &rtree,
+            &SvgOptions {
+                size: SvgSize::Exact(200, 200),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    }
+
+    fn max_difference(a: &RgbaImage, b: &RgbaImage) -> u8 {
+        assert_eq!(a.dimensions(), b.dimensions());
+        a.as_raw()
+            .iter()
+            .zip(b.as_raw())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn optimize_keeps_rendering() {
+        let rtree = usvg::Tree::from_str(SVG, &usvg::Options::default()).unwrap();
+        let before = render(SVG);
+        let mut path_counts = Vec::new();
+        for merge_paths in [false, true] {
+            for minify in [false, true] {
+                let optimized = optimize_svg(
+                    &rtree,
+                    &SvgOptimize {
+                        enabled: true,
+                        precision: 3,
+                        merge_paths,
+                        minify,
+                    },
+                );
+                let after = render(&optimized);
+                assert!(max_difference(&before, &after) <= 2, "{optimized}");
+                path_counts.push(optimized.matches("<path").count());
+            }
+        }
+        // 只有两个不透明的红色图形合并, 重叠的、半透明的和颜色不同的都保留
+        assert_eq!(path_counts, [8, 8, 7, 7]);
+    }
+
+    #[test]
+    fn render_rejects_oversized_padding() {
+        let rtree = usvg::Tree::from_str(SVG, &usvg::Options::default()).unwrap();
+        let options = SvgOptions {
+            padding: u32::MAX / 2,
+            ..Default::default()
+        };
+        assert!(render_svg(&rtree, &options).is_err());
+    }
+
+    #[test]
+    fn compact_path_data_only_touches_paths() {
+        let svg = r#"<g id="a b"><path fill="red" d="M 1 2 L 3 -4 C 1.5 2 -3 4 5 6 Z"/></g>"#;
+        assert_eq!(
+            compact_path_data(svg),
+            r#"<g id="a b"><path fill="red" d="M1 2L3-4C1.5 2-3 4 5 6Z"/></g>"#
+        );
+    }
+}
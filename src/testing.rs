@@ -0,0 +1,47 @@
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// 测试用的临时文件, 文件名带进程号和序号, 并行的测试不会互相覆盖, 用完自动删除
+pub struct TempFile(PathBuf);
+
+impl TempFile {
+    pub fn new(extension: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        TempFile(std::env::temp_dir().join(format!(
+            "imgzap-test-{}-{}.{extension}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+
+    /// 写入内容后返回
+    pub fn with_data(extension: &str, data: &[u8]) -> Self {
+        let file = TempFile::new(extension);
+        std::fs::write(&file, data).unwrap();
+        file
+    }
+}
+
+impl Deref for TempFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempFile {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
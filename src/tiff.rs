@@ -0,0 +1,380 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Cursor, Read},
+    path::Path,
+};
+
+use ::tiff::ColorType as TiffColorType;
+use ::tiff::decoder::{ChunkType, Decoder, DecodingResult};
+use ::tiff::encoder::{
+    TiffEncoder, colortype,
+    compression::{Compression, Deflate, Lzw, Packbits, Uncompressed},
+};
+use ::tiff::tags::Tag;
+use anyhow::{Context, Result};
+use image::{DynamicImage, ImageBuffer};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{tr, trf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TiffCompression {
+    None,
+    #[default]
+    Lzw,
+    Deflate,
+    PackBits,
+}
+
+impl TiffCompression {
+    pub const ALL: [TiffCompression; 4] = [
+        TiffCompression::None,
+        TiffCompression::Lzw,
+        TiffCompression::Deflate,
+        TiffCompression::PackBits,
+    ];
+}
+
+impl fmt::Display for TiffCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            TiffCompression::None => "不压缩",
+            TiffCompression::Lzw => "LZW",
+            TiffCompression::Deflate => "Deflate",
+            TiffCompression::PackBits => "PackBits",
+        }))
+    }
+}
+
+/// 每一页写成一个 IFD, 页面需事先转换为 TIFF 支持的颜色类型 (见 depth::fit)
+pub fn save_tiff(pages: &[DynamicImage], path: &Path, compression: TiffCompression) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create file '{path:?}'"))?;
+    let mut encoder = TiffEncoder::new(BufWriter::new(file))?;
+
+    for page in pages {
+        match compression {
+            TiffCompression::None => write_page(&mut encoder, page, Uncompressed)?,
+            TiffCompression::Lzw => write_page(&mut encoder, page, Lzw)?,
+            TiffCompression::Deflate => write_page(&mut encoder, page, Deflate::default())?,
+            TiffCompression::PackBits => write_page(&mut encoder, page, Packbits)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_page<D: Compression>(
+    encoder: &mut TiffEncoder<BufWriter<File>>,
+    page: &DynamicImage,
+    compression: D,
+) -> Result<()> {
+    let (width, height) = (page.width(), page.height());
+    match page {
+        DynamicImage::ImageLuma8(image) => encoder
+            .write_image_with_compression::<colortype::Gray8, D>(width, height, compression, image),
+        DynamicImage::ImageRgb8(image) => encoder
+            .write_image_with_compression::<colortype::RGB8, D>(width, height, compression, image),
+        DynamicImage::ImageRgba8(image) => encoder
+            .write_image_with_compression::<colortype::RGBA8, D>(width, height, compression, image),
+        DynamicImage::ImageLuma16(image) => encoder
+            .write_image_with_compression::<colortype::Gray16, D>(
+                width,
+                height,
+                compression,
+                image,
+            ),
+        DynamicImage::ImageRgb16(image) => encoder
+            .write_image_with_compression::<colortype::RGB16, D>(width, height, compression, image),
+        DynamicImage::ImageRgba16(image) => encoder
+            .write_image_with_compression::<colortype::RGBA16, D>(
+                width,
+                height,
+                compression,
+                image,
+            ),
+        page => anyhow::bail!("TIFF does not support color type {:?}", page.color()),
+    }
+    .with_context(|| "Failed to encode TIFF page")
+}
+
+/// 只读 IFD, 不解码像素
+pub fn page_count(path: &Path) -> Result<usize> {
+    let file = File::open(path).with_context(|| format!("Failed to open file '{path:?}'"))?;
+    let mut decoder = Decoder::new(std::io::BufReader::new(file))?;
+    let mut count = 1;
+    while decoder.more_images() {
+        decoder.next_image()?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// 读取一次文件, 解码所有页面; 无法解码的页面跳过, 返回提示
+pub fn read_pages(path: &Path) -> Result<(Vec<DynamicImage>, Vec<String>)> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read file '{path:?}'"))?;
+    let mut decoder = Decoder::new(Cursor::new(data.as_slice()))?;
+    let mut pages = Vec::new();
+    let mut notes = Vec::new();
+    for number in 1.. {
+        match read_page(&mut decoder, &data) {
+            Ok(page) => pages.push(page),
+            Err(e) => {
+                println!("Failed to decode TIFF page {number} of {path:?}\n{e:?}");
+                notes.push(trf("第 {} 页无法解码, 已跳过: {}", &[&number, &e]));
+            }
+        }
+
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image()?;
+    }
+    anyhow::ensure!(!pages.is_empty(), "No TIFF page could be decoded");
+
+    Ok((pages, notes))
+}
+
+/// 灰度/RGB (可带 alpha) 的 8/16 位整数和 32 位浮点, CMYK 转为 RGB,
+/// 调色板和 1/2/4 位灰度由 read_packed 解码
+fn read_page(decoder: &mut Decoder<Cursor<&[u8]>>, data: &[u8]) -> Result<DynamicImage> {
+    let (width, height) = decoder.dimensions()?;
+    let color = match decoder.colortype() {
+        Ok(TiffColorType::Gray(1 | 2 | 4)) => return read_packed(decoder, data),
+        Ok(color) => color,
+        // tiff 不支持调色板, 颜色类型直接报错
+        Err(_) if decoder.find_tag_unsigned::<u16>(Tag::PhotometricInterpretation)? == Some(3) => {
+            return read_packed(decoder, data);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let page = match (color, decoder.read_image()?) {
+        (TiffColorType::Gray(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        (TiffColorType::GrayA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        (TiffColorType::RGB(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        }
+        (TiffColorType::RGBA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        (TiffColorType::CMYK(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, cmyk_to_rgb(&data, u8::MAX))
+                .map(DynamicImage::ImageRgb8)
+        }
+        (TiffColorType::Gray(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16)
+        }
+        (TiffColorType::GrayA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA16)
+        }
+        (TiffColorType::RGB(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16)
+        }
+        (TiffColorType::RGBA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba16)
+        }
+        (TiffColorType::CMYK(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, cmyk_to_rgb(&data, u16::MAX))
+                .map(DynamicImage::ImageRgb16)
+        }
+        (TiffColorType::RGB(32), DecodingResult::F32(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb32F)
+        }
+        (TiffColorType::RGBA(32), DecodingResult::F32(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba32F)
+        }
+        (color, _) => anyhow::bail!("Unsupported TIFF color type {color:?}"),
+    };
+
+    page.context("Invalid TIFF page")
+}
+
+/// 不带色彩管理的简单换算, 与 image 解码 CMYK JPEG 的方式一致
+fn cmyk_to_rgb<T>(data: &[T], max: T) -> Vec<T>
+where
+    T: Copy + Into<u32> + TryFrom<u32>,
+{
+    let max_value: u32 = max.into();
+    data.chunks_exact(4)
+        .flat_map(|cmyk| {
+            let k = max_value - cmyk[3].into();
+            [0, 1, 2].map(|i| {
+                let value = (max_value - cmyk[i].into()) * k / max_value;
+                T::try_from(value).unwrap_or(max)
+            })
+        })
+        .collect()
+}
+
+/// tiff 无法解码的调色板 (1-8 位) 和 1/2/4 位灰度页面: 直接读取条带, 解压后按位展开
+fn read_packed(decoder: &mut Decoder<Cursor<&[u8]>>, data: &[u8]) -> Result<DynamicImage> {
+    let (width, height) = decoder.dimensions()?;
+    let bits = decoder.get_tag_unsigned::<u16>(Tag::BitsPerSample)?;
+    let photometric = decoder.get_tag_unsigned::<u16>(Tag::PhotometricInterpretation)?;
+    let compression = decoder
+        .find_tag_unsigned::<u16>(Tag::Compression)?
+        .unwrap_or(1);
+    anyhow::ensure!(
+        matches!(bits, 1 | 2 | 4 | 8),
+        "Unsupported TIFF bit depth {bits}"
+    );
+    anyhow::ensure!(
+        decoder.get_chunk_type() == ChunkType::Strip,
+        "Tiled palette or bilevel TIFF pages are not supported"
+    );
+    anyhow::ensure!(
+        decoder
+            .find_tag_unsigned::<u16>(Tag::Predictor)?
+            .unwrap_or(1)
+            == 1,
+        "TIFF predictor is not supported for palette or bilevel pages"
+    );
+
+    let offsets = decoder.get_tag_u64_vec(Tag::StripOffsets)?;
+    let counts = decoder.get_tag_u64_vec(Tag::StripByteCounts)?;
+    let rows_per_strip = decoder
+        .find_tag_unsigned::<u32>(Tag::RowsPerStrip)?
+        .unwrap_or(height)
+        .min(height) as usize;
+    // 每行按字节对齐
+    let stride = (width as usize * bits as usize).div_ceil(8);
+    let mut packed = Vec::with_capacity(stride * height as usize);
+    for (strip, (offset, count)) in offsets.iter().zip(&counts).enumerate() {
+        let raw = data
+            .get(*offset as usize..(*offset + *count) as usize)
+            .context("TIFF strip is truncated")?;
+        let mut rows = decompress(compression, raw)?;
+        let row_count = (height as usize)
+            .saturating_sub(strip * rows_per_strip)
+            .min(rows_per_strip);
+        rows.resize(row_count * stride, 0);
+        packed.extend(rows);
+    }
+    packed.resize(stride * height as usize, 0);
+
+    let max = (1u16 << bits) - 1;
+    let samples = (0..height as usize).flat_map(|y| {
+        let row = &packed[y * stride..(y + 1) * stride];
+        (0..width as usize).map(move |x| {
+            let bit = x * bits as usize;
+            (row[bit / 8] as u16 >> (8 - bits as usize - bit % 8)) & max
+        })
+    });
+    let gray = |value: u16| (value * 255 / max) as u8;
+
+    let page = match photometric {
+        0 => ImageBuffer::from_raw(width, height, samples.map(|v| 255 - gray(v)).collect())
+            .map(DynamicImage::ImageLuma8),
+        1 => ImageBuffer::from_raw(width, height, samples.map(gray).collect())
+            .map(DynamicImage::ImageLuma8),
+        3 => {
+            // 颜色表依次是全部红, 全部绿, 全部蓝, 16 位
+            let colormap = decoder.get_tag_u16_vec(Tag::ColorMap)?;
+            let size = 1usize << bits;
+            anyhow::ensure!(colormap.len() >= size * 3, "TIFF color map is too short");
+            let rgb = samples
+                .flat_map(|index| {
+                    [0, 1, 2].map(|i| (colormap[i * size + index as usize] >> 8) as u8)
+                })
+                .collect();
+            ImageBuffer::from_raw(width, height, rgb).map(DynamicImage::ImageRgb8)
+        }
+        _ => anyhow::bail!("Unsupported TIFF photometric interpretation {photometric}"),
+    };
+
+    page.context("Invalid TIFF page")
+}
+
+fn decompress(compression: u16, raw: &[u8]) -> Result<Vec<u8>> {
+    match compression {
+        1 => Ok(raw.to_vec()),
+        5 => weezl::decode::Decoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8)
+            .decode(raw)
+            .context("Failed to decompress LZW strip"),
+        8 | 32946 => {
+            let mut rows = Vec::new();
+            flate2::read::ZlibDecoder::new(raw)
+                .read_to_end(&mut rows)
+                .context("Failed to decompress Deflate strip")?;
+            Ok(rows)
+        }
+        32773 => Ok(unpack_bits(raw)),
+        compression => anyhow::bail!("Unsupported TIFF compression {compression}"),
+    }
+}
+
+/// PackBits: 控制字节 n >= 0 复制后面 n + 1 个字节, n < 0 (除 -128) 重复下一个字节 1 - n 次
+fn unpack_bits(raw: &[u8]) -> Vec<u8> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < raw.len() {
+        let n = raw[i] as i8;
+        i += 1;
+        match n {
+            0.. => {
+                let end = (i + n as usize + 1).min(raw.len());
+                rows.extend_from_slice(&raw[i..end]);
+                i = end;
+            }
+            -128 => {}
+            _ => {
+                if let Some(&byte) = raw.get(i) {
+                    rows.extend(std::iter::repeat_n(byte, 1 + (-n) as usize));
+                }
+                i += 1;
+            }
+        }
+    }
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempFile;
+
+    // RGB, 1 位灰度, 4 位调色板, CMYK, 8 位调色板各一页, 每页 4x2, 不压缩
+    const MIXED: &[u8] = include_bytes!("../tests/data/mixed-pages.tiff");
+
+    #[test]
+    fn read_mixed_pages() {
+        let file = TempFile::with_data("tiff", MIXED);
+        let (pages, skipped) = read_pages(&file).unwrap();
+        assert!(skipped.is_empty(), "{skipped:?}");
+        assert_eq!(pages.len(), 5);
+
+        let rgb = pages[0].to_rgb8();
+        assert_eq!(rgb.get_pixel(0, 0).0, [255, 0, 0]);
+
+        // 第一行 1010, 第二行 0101
+        let bilevel = pages[1].to_luma8();
+        assert_eq!(bilevel.as_raw(), &[255, 0, 255, 0, 0, 255, 0, 255]);
+
+        let palette = pages[2].to_rgb8();
+        assert_eq!(palette.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(palette.get_pixel(1, 0).0, [255, 0, 0]);
+        assert_eq!(palette.get_pixel(0, 1).0, [0, 0, 255]);
+
+        let cmyk = pages[3].to_rgb8();
+        assert_eq!(cmyk.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(cmyk.get_pixel(3, 0).0, [0, 0, 0]);
+
+        let palette = pages[4].to_rgb8();
+        assert_eq!(palette.get_pixel(2, 0).0, [0, 255, 0]);
+    }
+
+    #[test]
+    fn unpack_bits_runs() {
+        // 复制 3 个字节, 重复 0xAA 4 次
+        assert_eq!(
+            unpack_bits(&[2, 1, 2, 3, (-3i8) as u8, 0xAA]),
+            [1, 2, 3, 0xAA, 0xAA, 0xAA, 0xAA]
+        );
+    }
+}
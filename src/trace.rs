@@ -0,0 +1,182 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use visioncortex::PathSimplifyMode;
+use vtracer::{ColorMode, Config, Hierarchical, Preset};
+
+use crate::i18n::tr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TracePreset {
+    Default,
+    /// 颜色少、边缘清晰的图标和标志, 路径尽量少
+    Logo,
+    Poster,
+    Photo,
+    /// 黑白线稿
+    LineArt,
+    /// 手动调整过参数
+    Custom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceCurve {
+    Spline,
+    Polygon,
+    Pixel,
+}
+
+/// vtracer 的矢量化参数, 取值范围与 vtracer 命令行一致
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TraceOptions {
+    pub preset: TracePreset,
+    /// 彩色或黑白
+    pub color: bool,
+    /// 堆叠 (stacked) 或挖空 (cutout)
+    pub stacked: bool,
+    pub curve: TraceCurve,
+    /// 0 - 128, 丢弃小于该尺寸的斑点
+    pub filter_speckle: u32,
+    /// 1 - 8, 颜色精度 (有效位数)
+    pub color_precision: i32,
+    /// 0 - 255, 相邻颜色层的差值
+    pub layer_difference: i32,
+    /// 0 - 180, 角度阈值
+    pub corner_threshold: i32,
+    /// 3.5 - 10, 线段长度阈值
+    pub length_threshold: f64,
+    /// 0 - 180, 样条拼接阈值
+    pub splice_threshold: i32,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TracePreset::Default.options()
+    }
+}
+
+impl TraceOptions {
+    /// 限制在设置界面滑块的范围内
+    pub fn sanitize(&mut self) {
+        self.filter_speckle = self.filter_speckle.min(128);
+        self.color_precision = self.color_precision.clamp(1, 8);
+        self.layer_difference = self.layer_difference.clamp(0, 255);
+        self.corner_threshold = self.corner_threshold.clamp(0, 180);
+        self.length_threshold = if self.length_threshold.is_finite() {
+            self.length_threshold.clamp(3.5, 10.0)
+        } else {
+            TraceOptions::default().length_threshold
+        };
+        self.splice_threshold = self.splice_threshold.clamp(0, 180);
+    }
+}
+
+impl TracePreset {
+    pub const ALL: [TracePreset; 6] = [
+        TracePreset::Default,
+        TracePreset::Logo,
+        TracePreset::Poster,
+        TracePreset::Photo,
+        TracePreset::LineArt,
+        TracePreset::Custom,
+    ];
+
+    pub fn options(self) -> TraceOptions {
+        let config = match self {
+            TracePreset::Default | TracePreset::Custom => Config::default(),
+            TracePreset::Logo => Config {
+                filter_speckle: 8,
+                color_precision: 4,
+                layer_difference: 32,
+                corner_threshold: 60,
+                length_threshold: 6.0,
+                splice_threshold: 45,
+                ..Config::from_preset(Preset::Poster)
+            },
+            TracePreset::Poster => Config::from_preset(Preset::Poster),
+            TracePreset::Photo => Config::from_preset(Preset::Photo),
+            TracePreset::LineArt => Config::from_preset(Preset::Bw),
+        };
+
+        TraceOptions {
+            preset: self,
+            ..TraceOptions::from_config(&config)
+        }
+    }
+}
+
+impl fmt::Display for TracePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            TracePreset::Default => "默认",
+            TracePreset::Logo => "标志",
+            TracePreset::Poster => "海报",
+            TracePreset::Photo => "照片",
+            TracePreset::LineArt => "黑白线稿",
+            TracePreset::Custom => "自定义",
+        }))
+    }
+}
+
+impl TraceCurve {
+    pub const ALL: [TraceCurve; 3] = [TraceCurve::Spline, TraceCurve::Polygon, TraceCurve::Pixel];
+}
+
+impl fmt::Display for TraceCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(tr(match self {
+            TraceCurve::Spline => "曲线",
+            TraceCurve::Polygon => "多边形",
+            TraceCurve::Pixel => "像素",
+        }))
+    }
+}
+
+impl TraceOptions {
+    fn from_config(config: &Config) -> Self {
+        TraceOptions {
+            preset: TracePreset::Custom,
+            color: matches!(config.color_mode, ColorMode::Color),
+            stacked: matches!(config.hierarchical, Hierarchical::Stacked),
+            curve: match config.mode {
+                PathSimplifyMode::Spline => TraceCurve::Spline,
+                PathSimplifyMode::Polygon => TraceCurve::Polygon,
+                PathSimplifyMode::None => TraceCurve::Pixel,
+            },
+            filter_speckle: config.filter_speckle as u32,
+            color_precision: config.color_precision,
+            layer_difference: config.layer_difference,
+            corner_threshold: config.corner_threshold,
+            length_threshold: config.length_threshold,
+            splice_threshold: config.splice_threshold,
+        }
+    }
+
+    pub fn to_config(self) -> Config {
+        Config {
+            color_mode: if self.color {
+                ColorMode::Color
+            } else {
+                ColorMode::Binary
+            },
+            hierarchical: if self.stacked {
+                Hierarchical::Stacked
+            } else {
+                Hierarchical::Cutout
+            },
+            mode: match self.curve {
+                TraceCurve::Spline => PathSimplifyMode::Spline,
+                TraceCurve::Polygon => PathSimplifyMode::Polygon,
+                TraceCurve::Pixel => PathSimplifyMode::None,
+            },
+            filter_speckle: self.filter_speckle as usize,
+            color_precision: self.color_precision,
+            layer_difference: self.layer_difference,
+            corner_threshold: self.corner_threshold,
+            length_threshold: self.length_threshold,
+            splice_threshold: self.splice_threshold,
+            ..Config::default()
+        }
+    }
+}